@@ -0,0 +1,427 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, GenericArgument, Member};
+use syn::{LitStr, Path, PathArguments, Type};
+
+#[proc_macro_derive(PascalFile, attributes(pascal_file))]
+pub fn derive_pascal_file(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+struct ContainerOptions {
+    unit: Option<Type>,
+    text: bool,
+    encoding: Option<LitStr>,
+    encoding_table: Option<Path>,
+    backend: Option<Type>,
+    error_state_mode: bool,
+}
+
+fn parse_container_options(input: &DeriveInput) -> syn::Result<ContainerOptions> {
+    let mut options = ContainerOptions {
+        unit: None,
+        text: false,
+        encoding: None,
+        encoding_table: None,
+        backend: None,
+        error_state_mode: false,
+    };
+    for attr in &input.attrs {
+        if !attr.path().is_ident("pascal_file") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("unit") {
+                let unit: LitStr = meta.value()?.parse()?;
+                options.unit = Some(unit.parse()?);
+            } else if meta.path.is_ident("text") {
+                options.text = true;
+            } else if meta.path.is_ident("binary") {
+                options.text = false;
+            } else if meta.path.is_ident("encoding") {
+                options.encoding = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("encoding_table") {
+                let table: LitStr = meta.value()?.parse()?;
+                options.encoding_table = Some(table.parse()?);
+            } else if meta.path.is_ident("backend") {
+                let backend: LitStr = meta.value()?.parse()?;
+                options.backend = Some(backend.parse()?);
+            } else if meta.path.is_ident("error_state_mode") {
+                options.error_state_mode = true;
+            } else {
+                return Err(meta.error("unknown pascal_file option"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(options)
+}
+
+fn last_segment_is(ty: &Type, name: &str) -> bool {
+    match ty {
+        Type::Path(ty) => ty
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == name),
+        _ => false,
+    }
+}
+
+fn first_type_argument(ty: &Type) -> Option<&Type> {
+    type_argument(ty, 0)
+}
+
+fn type_argument(ty: &Type, index: usize) -> Option<&Type> {
+    let segment = match ty {
+        Type::Path(ty) => ty.path.segments.last()?,
+        _ => return None,
+    };
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .nth(index),
+        _ => None,
+    }
+}
+
+fn is_state_field(ty: &Type) -> bool {
+    last_segment_is(ty, "FileState")
+}
+
+fn is_error_field(ty: &Type) -> bool {
+    last_segment_is(ty, "Option")
+        && first_type_argument(ty).is_some_and(|ty| last_segment_is(ty, "PascalIoError"))
+}
+
+fn find_field<'a>(
+    input: &'a DeriveInput,
+    fields: &'a Fields,
+    marker: &str,
+    fallback: fn(&Type) -> bool,
+) -> syn::Result<(Member, &'a Type)> {
+    let mut marked = None;
+    let mut detected = vec![];
+    for (index, field) in fields.iter().enumerate() {
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(index.into()),
+        };
+        for attr in &field.attrs {
+            if !attr.path().is_ident("pascal_file") {
+                continue;
+            }
+            let mut is_marked = false;
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("state") || meta.path.is_ident("error") {
+                    is_marked |= meta.path.is_ident(marker);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `state` or `error`"))
+                }
+            })?;
+            if is_marked {
+                if marked.is_some() {
+                    return Err(Error::new(
+                        attr.span(),
+                        format!("more than one field marked `{}`", marker),
+                    ));
+                }
+                marked = Some((member.clone(), &field.ty));
+            }
+        }
+        if fallback(&field.ty) {
+            detected.push((member, &field.ty));
+        }
+    }
+    if let Some(field) = marked {
+        return Ok(field);
+    }
+    if detected.len() == 1 {
+        return Ok(detected.pop().unwrap());
+    }
+    Err(Error::new(
+        input.ident.span(),
+        format!(
+            "mark the {} field with `#[pascal_file({})]`",
+            marker, marker
+        ),
+    ))
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(Error::new(
+                input.ident.span(),
+                "PascalFile can only be derived for structs",
+            ))
+        }
+    };
+    let options = parse_container_options(&input)?;
+    let (state_member, state_type) = find_field(&input, fields, "state", is_state_field)?;
+    let (error_member, _) = find_field(&input, fields, "error", is_error_field)?;
+    let unit = match options.unit {
+        Some(unit) => unit,
+        None => first_type_argument(state_type).cloned().ok_or_else(|| {
+            Error::new(
+                state_type.span(),
+                "cannot infer the unit type, use `#[pascal_file(unit = \"...\")]`",
+            )
+        })?,
+    };
+    // `FileState<Unit, Backend>` names its backend, `FileState<Unit>` uses the default.
+    let backend = match options
+        .backend
+        .as_ref()
+        .or_else(|| type_argument(state_type, 1))
+    {
+        Some(backend) => quote!(#backend),
+        None => quote!(::pascal_io::BoxedBackend),
+    };
+
+    let text_file = quote!(::pascal_io::TextFile<u8>);
+    let byte_file = quote!(::pascal_io::ByteFile<#backend>);
+    let file_backend = quote!(<#backend as ::pascal_io::FileBackend>);
+    let unit_methods = if options.text {
+        let encoding = match (&options.encoding, &options.encoding_table) {
+            (Some(name), Some(_)) => {
+                return Err(Error::new(
+                    name.span(),
+                    "`encoding` and `encoding_table` are exclusive",
+                ))
+            }
+            (None, Some(table)) => quote!(::pascal_io::TextEncoding::Table(&#table)),
+            (None, None) => quote!(::pascal_io::TextEncoding::Utf8),
+            (Some(name), None) => match name.value().to_ascii_lowercase().as_str() {
+                "utf8" | "utf-8" => quote!(::pascal_io::TextEncoding::Utf8),
+                "latin1" | "latin-1" | "iso-8859-1" => quote!(::pascal_io::TextEncoding::Latin1),
+                "ascii" => quote!(::pascal_io::TextEncoding::Ascii),
+                "windows-1252" | "cp1252" => quote!(::pascal_io::TextEncoding::Windows1252),
+                "cp437" | "ibm437" => quote!(::pascal_io::TextEncoding::Cp437),
+                _ => return Err(Error::new(name.span(), "unknown encoding")),
+            },
+        };
+        quote! {
+            fn is_text_file() -> bool {
+                true
+            }
+
+            fn is_eoln_unit(unit: &#unit) -> bool {
+                ::pascal_io::TextUnit::to_char(*unit) == '\n'
+            }
+
+            fn eoln_unit() -> #unit {
+                <#unit as ::pascal_io::TextUnit>::eoln_unit()
+            }
+
+            fn open_text_file_for_read(
+                path: &str,
+            ) -> ::core::result::Result<
+                (#file_backend::LineReader, bool),
+                ::pascal_io::PascalIoError,
+            > {
+                #file_backend::open_line_reader(path)
+            }
+
+            fn open_binary_file_for_read(
+                _: &str,
+            ) -> ::core::result::Result<#file_backend::Reader, ::pascal_io::PascalIoError> {
+                Err(::pascal_io::PascalIoError::Io(
+                    ::std::io::ErrorKind::Unsupported.into(),
+                ))
+            }
+
+            fn convert_line_bytes_crlf_to_lf(input: &mut ::std::vec::Vec<u8>) {
+                <#text_file as ::pascal_io::PascalFile>::convert_line_bytes_crlf_to_lf(input)
+            }
+
+            fn convert_line_bytes_to_units(
+                input: &[u8],
+                units: &mut ::std::vec::Vec<#unit>,
+            ) -> ::core::result::Result<(), ::pascal_io::PascalIoError> {
+                #encoding.convert_bytes_to_units(input, units)
+            }
+
+            fn convert_blob_to_unit(_: &[u8]) -> #unit {
+                unreachable!("text files are never read in block mode")
+            }
+
+            fn convert_unit_to_blob(
+                data: #unit,
+                f: &mut dyn for<'a> FnMut(&'a [u8]),
+            ) -> ::core::result::Result<(), ::pascal_io::PascalIoError> {
+                #encoding.convert_unit_to_bytes(data, f)
+            }
+
+            fn text_encoding() -> ::pascal_io::TextEncoding {
+                #encoding
+            }
+
+            fn convert_str_to_units(
+                input: &str,
+                units: &mut ::std::vec::Vec<#unit>,
+            ) -> ::core::result::Result<(), ::pascal_io::PascalIoError> {
+                #encoding.convert_str_to_units(input, units)
+            }
+
+            fn convert_unit_to_str(
+                data: #unit,
+                text: &mut ::std::string::String,
+            ) -> ::core::result::Result<(), ::pascal_io::PascalIoError> {
+                #encoding.convert_unit_to_str(data, text)
+            }
+        }
+    } else {
+        if let Some(encoding) = &options.encoding {
+            return Err(Error::new(
+                encoding.span(),
+                "`encoding` only applies to text files",
+            ));
+        }
+        if let Some(table) = &options.encoding_table {
+            return Err(Error::new(
+                table.span(),
+                "`encoding_table` only applies to text files",
+            ));
+        }
+        quote! {
+            fn is_text_file() -> bool {
+                false
+            }
+
+            fn is_eoln_unit(_: &#unit) -> bool {
+                false
+            }
+
+            fn eoln_unit() -> #unit {
+                <#unit as ::core::default::Default>::default()
+            }
+
+            fn open_text_file_for_read(
+                path: &str,
+            ) -> ::core::result::Result<
+                (#file_backend::LineReader, bool),
+                ::pascal_io::PascalIoError,
+            > {
+                <#byte_file as ::pascal_io::PascalFile>::open_text_file_for_read(path)
+            }
+
+            fn open_binary_file_for_read(
+                path: &str,
+            ) -> ::core::result::Result<#file_backend::Reader, ::pascal_io::PascalIoError> {
+                <#byte_file as ::pascal_io::PascalFile>::open_binary_file_for_read(path)
+            }
+
+            fn supports_random_access() -> bool {
+                <#byte_file as ::pascal_io::PascalFile>::supports_random_access()
+            }
+
+            fn open_binary_file_for_random_read(
+                path: &str,
+            ) -> ::core::result::Result<
+                #file_backend::RandomReader,
+                ::pascal_io::PascalIoError,
+            > {
+                <#byte_file as ::pascal_io::PascalFile>::open_binary_file_for_random_read(path)
+            }
+
+            fn open_file_for_random_write(
+                path: &str,
+            ) -> ::core::result::Result<
+                #file_backend::RandomWriter,
+                ::pascal_io::PascalIoError,
+            > {
+                <#byte_file as ::pascal_io::PascalFile>::open_file_for_random_write(path)
+            }
+
+            fn open_binary_file_for_update(
+                path: &str,
+            ) -> ::core::result::Result<#file_backend::Updater, ::pascal_io::PascalIoError> {
+                <#byte_file as ::pascal_io::PascalFile>::open_binary_file_for_update(path)
+            }
+
+            fn convert_line_bytes_crlf_to_lf(_: &mut ::std::vec::Vec<u8>) {
+                unreachable!("binary files are never read in line mode")
+            }
+
+            fn convert_line_bytes_to_units(
+                _: &[u8],
+                _: &mut ::std::vec::Vec<#unit>,
+            ) -> ::core::result::Result<(), ::pascal_io::PascalIoError> {
+                unreachable!("binary files are never read in line mode")
+            }
+
+            fn convert_blob_to_unit(input: &[u8]) -> #unit {
+                <#unit as ::pascal_io::FromBlob>::from_blob(input)
+            }
+
+            fn convert_unit_to_blob(
+                data: #unit,
+                f: &mut dyn for<'a> FnMut(&'a [u8]),
+            ) -> ::core::result::Result<(), ::pascal_io::PascalIoError> {
+                let blob = ::pascal_io::ToBlob::to_blob(&data);
+                f(::core::borrow::Borrow::borrow(&blob));
+                Ok(())
+            }
+        }
+    };
+    let error_state_mode = if options.error_state_mode {
+        quote! {
+            fn error_state_mode(&self) -> bool {
+                true
+            }
+        }
+    } else {
+        quote!()
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::pascal_io::PascalFile for #name #ty_generics #where_clause {
+            type Unit = #unit;
+
+            type Backend = #backend;
+
+            #unit_methods
+
+            fn open_file_for_write(
+                path: &str,
+            ) -> ::core::result::Result<#file_backend::Writer, ::pascal_io::PascalIoError> {
+                <#byte_file as ::pascal_io::PascalFile>::open_file_for_write(path)
+            }
+
+            fn file_state(&self) -> &::pascal_io::FileState<#unit, #backend> {
+                &self.#state_member
+            }
+
+            fn file_state_mut(&mut self) -> &mut ::pascal_io::FileState<#unit, #backend> {
+                &mut self.#state_member
+            }
+
+            fn error_state(&self) -> ::core::option::Option<&::pascal_io::PascalIoError> {
+                self.#error_member.as_ref()
+            }
+
+            fn set_error_state(
+                &mut self,
+                error_state: ::core::option::Option<::pascal_io::PascalIoError>,
+            ) {
+                self.#error_member = error_state;
+            }
+
+            #error_state_mode
+        }
+    })
+}
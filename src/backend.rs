@@ -0,0 +1,154 @@
+use crate::stdio::unsupported_operation;
+use crate::{PascalIoError, ReadLine, ReadSeek, ReadWriteSeek, WriteSeek};
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, IsTerminal, Read, Write};
+
+// The stream types a `FileState` reads and writes through, and how they are
+// opened from a path. `BoxedBackend` keeps them behind trait objects so any
+// stream fits; `FsBackend` uses the `File` types directly, letting `get` and
+// `put` be monomorphized down to the buffer accesses, as in
+// `TextFile<U, FsBackend>` and `ByteFile<FsBackend>`. Its writers are plain
+// files, `FileState` already buffers the output.
+pub trait FileBackend {
+    type LineReader: ReadLine;
+    type Reader: Read;
+    type RandomReader: ReadSeek;
+    type Writer: Write;
+    type RandomWriter: WriteSeek;
+    type Updater: ReadWriteSeek;
+
+    // Also tells whether the file is a terminal.
+    fn open_line_reader(path: &str) -> Result<(Self::LineReader, bool), PascalIoError>;
+
+    fn open_reader(path: &str) -> Result<Self::Reader, PascalIoError>;
+
+    fn open_random_reader(path: &str) -> Result<Self::RandomReader, PascalIoError>;
+
+    fn open_writer(path: &str) -> Result<Self::Writer, PascalIoError>;
+
+    fn open_random_writer(path: &str) -> Result<Self::RandomWriter, PascalIoError>;
+
+    fn open_updater(path: &str) -> Result<Self::Updater, PascalIoError>;
+
+    // Also returns the current length of the file.
+    fn open_appender(path: &str) -> Result<(Self::Writer, u64), PascalIoError>;
+
+    // Makes flushed data durable, for `close` of files opened with `/S`.
+    fn sync_writer(_writer: &mut Self::Writer) -> Result<(), PascalIoError> {
+        Err(unsupported_operation())
+    }
+
+    fn sync_random_writer(_writer: &mut Self::RandomWriter) -> Result<(), PascalIoError> {
+        Err(unsupported_operation())
+    }
+
+    fn sync_updater(_updater: &mut Self::Updater) -> Result<(), PascalIoError> {
+        Err(unsupported_operation())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BoxedBackend;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FsBackend;
+
+fn open_for_read(path: &str) -> Result<File, PascalIoError> {
+    let file = File::open(path)?;
+    if file.metadata()?.is_dir() {
+        return Err(PascalIoError::IsADirectory);
+    }
+    Ok(file)
+}
+
+impl FileBackend for FsBackend {
+    type LineReader = BufReader<File>;
+    type Reader = BufReader<File>;
+    type RandomReader = BufReader<File>;
+    type Writer = File;
+    type RandomWriter = File;
+    type Updater = File;
+
+    fn open_line_reader(path: &str) -> Result<(BufReader<File>, bool), PascalIoError> {
+        let file = open_for_read(path)?;
+        let is_terminal = file.is_terminal();
+        Ok((BufReader::new(file), is_terminal))
+    }
+
+    fn open_reader(path: &str) -> Result<BufReader<File>, PascalIoError> {
+        Ok(BufReader::new(open_for_read(path)?))
+    }
+
+    fn open_random_reader(path: &str) -> Result<BufReader<File>, PascalIoError> {
+        Ok(BufReader::new(open_for_read(path)?))
+    }
+
+    fn open_writer(path: &str) -> Result<File, PascalIoError> {
+        Ok(File::create(path)?)
+    }
+
+    fn open_random_writer(path: &str) -> Result<File, PascalIoError> {
+        Ok(File::create(path)?)
+    }
+
+    fn open_updater(path: &str) -> Result<File, PascalIoError> {
+        Ok(OpenOptions::new().read(true).write(true).open(path)?)
+    }
+
+    fn open_appender(path: &str) -> Result<(File, u64), PascalIoError> {
+        let file = OpenOptions::new().append(true).create(true).open(path)?;
+        let file_len = file.metadata()?.len();
+        Ok((file, file_len))
+    }
+
+    fn sync_writer(writer: &mut File) -> Result<(), PascalIoError> {
+        Ok(writer.sync_all()?)
+    }
+
+    fn sync_random_writer(writer: &mut File) -> Result<(), PascalIoError> {
+        Ok(writer.sync_all()?)
+    }
+
+    fn sync_updater(updater: &mut File) -> Result<(), PascalIoError> {
+        Ok(updater.sync_all()?)
+    }
+}
+
+impl FileBackend for BoxedBackend {
+    type LineReader = Box<dyn ReadLine>;
+    type Reader = Box<dyn Read>;
+    type RandomReader = Box<dyn ReadSeek>;
+    type Writer = Box<dyn Write>;
+    type RandomWriter = Box<dyn WriteSeek>;
+    type Updater = Box<dyn ReadWriteSeek>;
+
+    fn open_line_reader(path: &str) -> Result<(Box<dyn ReadLine>, bool), PascalIoError> {
+        let (line_reader, is_terminal) = FsBackend::open_line_reader(path)?;
+        Ok((Box::new(line_reader), is_terminal))
+    }
+
+    fn open_reader(path: &str) -> Result<Box<dyn Read>, PascalIoError> {
+        Ok(Box::new(FsBackend::open_reader(path)?))
+    }
+
+    fn open_random_reader(path: &str) -> Result<Box<dyn ReadSeek>, PascalIoError> {
+        Ok(Box::new(FsBackend::open_random_reader(path)?))
+    }
+
+    fn open_writer(path: &str) -> Result<Box<dyn Write>, PascalIoError> {
+        Ok(Box::new(FsBackend::open_writer(path)?))
+    }
+
+    fn open_random_writer(path: &str) -> Result<Box<dyn WriteSeek>, PascalIoError> {
+        Ok(Box::new(FsBackend::open_random_writer(path)?))
+    }
+
+    fn open_updater(path: &str) -> Result<Box<dyn ReadWriteSeek>, PascalIoError> {
+        Ok(Box::new(FsBackend::open_updater(path)?))
+    }
+
+    fn open_appender(path: &str) -> Result<(Box<dyn Write>, u64), PascalIoError> {
+        let (writer, file_len) = FsBackend::open_appender(path)?;
+        Ok((Box::new(writer), file_len))
+    }
+}
@@ -0,0 +1,179 @@
+use crate::{PascalIoError, TextUnit};
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::fmt;
+
+// Maps each byte of a single-byte character set to a character, like the
+// `xchr` table of TeX. Encoding uses the first byte mapping to a character.
+#[derive(Clone, PartialEq, Eq)]
+pub struct CodeTable {
+    chars: [char; 256],
+}
+
+impl CodeTable {
+    pub const fn new(chars: [char; 256]) -> Self {
+        CodeTable { chars }
+    }
+
+    pub fn decode_byte(&self, byte: u8) -> char {
+        self.chars[usize::from(byte)]
+    }
+
+    pub fn encode_char(&self, c: char) -> Option<u8> {
+        self.chars.iter().position(|&x| x == c).map(|i| i as u8)
+    }
+}
+
+impl fmt::Debug for CodeTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CodeTable").finish_non_exhaustive()
+    }
+}
+
+#[rustfmt::skip]
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+#[rustfmt::skip]
+const CP437_HIGH: [char; 128] = [
+    '\u{00C7}', '\u{00FC}', '\u{00E9}', '\u{00E2}', '\u{00E4}', '\u{00E0}', '\u{00E5}', '\u{00E7}',
+    '\u{00EA}', '\u{00EB}', '\u{00E8}', '\u{00EF}', '\u{00EE}', '\u{00EC}', '\u{00C4}', '\u{00C5}',
+    '\u{00C9}', '\u{00E6}', '\u{00C6}', '\u{00F4}', '\u{00F6}', '\u{00F2}', '\u{00FB}', '\u{00F9}',
+    '\u{00FF}', '\u{00D6}', '\u{00DC}', '\u{00A2}', '\u{00A3}', '\u{00A5}', '\u{20A7}', '\u{0192}',
+    '\u{00E1}', '\u{00ED}', '\u{00F3}', '\u{00FA}', '\u{00F1}', '\u{00D1}', '\u{00AA}', '\u{00BA}',
+    '\u{00BF}', '\u{2310}', '\u{00AC}', '\u{00BD}', '\u{00BC}', '\u{00A1}', '\u{00AB}', '\u{00BB}',
+    '\u{2591}', '\u{2592}', '\u{2593}', '\u{2502}', '\u{2524}', '\u{2561}', '\u{2562}', '\u{2556}',
+    '\u{2555}', '\u{2563}', '\u{2551}', '\u{2557}', '\u{255D}', '\u{255C}', '\u{255B}', '\u{2510}',
+    '\u{2514}', '\u{2534}', '\u{252C}', '\u{251C}', '\u{2500}', '\u{253C}', '\u{255E}', '\u{255F}',
+    '\u{255A}', '\u{2554}', '\u{2569}', '\u{2566}', '\u{2560}', '\u{2550}', '\u{256C}', '\u{2567}',
+    '\u{2568}', '\u{2564}', '\u{2565}', '\u{2559}', '\u{2558}', '\u{2552}', '\u{2553}', '\u{256B}',
+    '\u{256A}', '\u{2518}', '\u{250C}', '\u{2588}', '\u{2584}', '\u{258C}', '\u{2590}', '\u{2580}',
+    '\u{03B1}', '\u{00DF}', '\u{0393}', '\u{03C0}', '\u{03A3}', '\u{03C3}', '\u{00B5}', '\u{03C4}',
+    '\u{03A6}', '\u{0398}', '\u{03A9}', '\u{03B4}', '\u{221E}', '\u{03C6}', '\u{03B5}', '\u{2229}',
+    '\u{2261}', '\u{00B1}', '\u{2265}', '\u{2264}', '\u{2320}', '\u{2321}', '\u{00F7}', '\u{2248}',
+    '\u{00B0}', '\u{2219}', '\u{00B7}', '\u{221A}', '\u{207F}', '\u{00B2}', '\u{25A0}', '\u{00A0}',
+];
+
+// How text files map between bytes and characters. Single-byte encodings
+// write `?` for characters they cannot represent, except `Ascii` which
+// rejects anything outside 7-bit ASCII in both directions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextEncoding {
+    Utf8,
+    Latin1,
+    Ascii,
+    Windows1252,
+    Cp437,
+    Table(&'static CodeTable),
+}
+
+impl TextEncoding {
+    pub(crate) fn decode_byte(self, byte: u8) -> Result<char, PascalIoError> {
+        match self {
+            TextEncoding::Utf8 => unreachable!("UTF-8 is not a single-byte encoding"),
+            TextEncoding::Table(table) => Ok(table.decode_byte(byte)),
+            _ if byte < 0x80 => Ok(char::from(byte)),
+            TextEncoding::Latin1 => Ok(char::from(byte)),
+            TextEncoding::Ascii => Err(PascalIoError::DecodeError),
+            TextEncoding::Windows1252 => Ok(match byte {
+                0x80..=0x9F => WINDOWS_1252_HIGH[usize::from(byte - 0x80)],
+                _ => char::from(byte),
+            }),
+            TextEncoding::Cp437 => Ok(CP437_HIGH[usize::from(byte - 0x80)]),
+        }
+    }
+
+    pub(crate) fn encode_char(self, c: char) -> Result<u8, PascalIoError> {
+        let byte = match self {
+            TextEncoding::Utf8 => unreachable!("UTF-8 is not a single-byte encoding"),
+            TextEncoding::Table(table) => table.encode_char(c),
+            _ if c.is_ascii() => Some(c as u8),
+            TextEncoding::Latin1 => u8::try_from(c).ok(),
+            TextEncoding::Ascii => return Err(PascalIoError::EncodeError(c)),
+            TextEncoding::Windows1252 => match WINDOWS_1252_HIGH.iter().position(|&x| x == c) {
+                Some(i) => Some(0x80 + i as u8),
+                None => u8::try_from(c).ok().filter(|&b| b >= 0xA0),
+            },
+            TextEncoding::Cp437 => CP437_HIGH
+                .iter()
+                .position(|&x| x == c)
+                .map(|i| 0x80 + i as u8),
+        };
+        Ok(byte.unwrap_or(b'?'))
+    }
+
+    pub fn convert_bytes_to_string(self, input: &[u8]) -> Result<Cow<'_, str>, PascalIoError> {
+        match self {
+            TextEncoding::Utf8 => Ok(String::from_utf8_lossy(input)),
+            _ => input.iter().map(|&b| self.decode_byte(b)).collect(),
+        }
+    }
+
+    pub fn convert_str_to_bytes(
+        self,
+        input: &str,
+        bytes: &mut Vec<u8>,
+    ) -> Result<(), PascalIoError> {
+        match self {
+            TextEncoding::Utf8 => bytes.extend_from_slice(input.as_bytes()),
+            _ => {
+                for c in input.chars() {
+                    bytes.push(self.encode_char(c)?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn convert_bytes_to_units<U: TextUnit>(
+        self,
+        input: &[u8],
+        units: &mut Vec<U>,
+    ) -> Result<(), PascalIoError> {
+        match self {
+            TextEncoding::Utf8 => U::convert_bytes_to_units(input, units),
+            _ => {
+                for &b in input {
+                    units.push(U::from_encoded_char(self, self.decode_byte(b)?)?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn convert_unit_to_bytes<U: TextUnit>(
+        self,
+        unit: U,
+        f: &mut dyn for<'a> FnMut(&'a [u8]),
+    ) -> Result<(), PascalIoError> {
+        match self {
+            TextEncoding::Utf8 => unit.convert_unit_to_bytes(f),
+            _ => f(&[self.encode_char(unit.to_encoded_char(self)?)?]),
+        }
+        Ok(())
+    }
+
+    pub fn convert_str_to_units<U: TextUnit>(
+        self,
+        input: &str,
+        units: &mut Vec<U>,
+    ) -> Result<(), PascalIoError> {
+        for c in input.chars() {
+            units.push(U::from_encoded_char(self, c)?);
+        }
+        Ok(())
+    }
+
+    pub fn convert_unit_to_str<U: TextUnit>(
+        self,
+        unit: U,
+        text: &mut String,
+    ) -> Result<(), PascalIoError> {
+        text.push(unit.to_encoded_char(self)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum PascalIoError {
+    NotFound,
+    PermissionDenied,
+    IsADirectory,
+    NotOpen,
+    WrongMode(&'static str),
+    Eof,
+    UndefinedBufferVariable,
+    DecodeError,
+    EncodeError(char),
+    TruncatedUnit,
+    InvalidNumber,
+    NumberOverflow,
+    NotSeekable,
+    InvalidOption(String),
+    LineTooLong,
+    LineLimitExceeded,
+    Io(io::Error),
+}
+
+impl PascalIoError {
+    /// Numeric code reported by `erstat`. Zero is reserved for "no error",
+    /// and the codes below are stable across releases.
+    pub fn code(&self) -> usize {
+        match self {
+            PascalIoError::NotFound => 1,
+            PascalIoError::PermissionDenied => 2,
+            PascalIoError::IsADirectory => 3,
+            PascalIoError::NotOpen => 4,
+            PascalIoError::WrongMode(_) => 5,
+            PascalIoError::Eof => 6,
+            PascalIoError::UndefinedBufferVariable => 7,
+            PascalIoError::DecodeError => 8,
+            PascalIoError::TruncatedUnit => 9,
+            PascalIoError::InvalidNumber => 10,
+            PascalIoError::NumberOverflow => 11,
+            PascalIoError::NotSeekable => 12,
+            PascalIoError::InvalidOption(_) => 13,
+            PascalIoError::EncodeError(_) => 14,
+            PascalIoError::LineTooLong => 15,
+            PascalIoError::LineLimitExceeded => 16,
+            PascalIoError::Io(_) => 255,
+        }
+    }
+
+    /// Whether the failure is in the data read rather than in the file, so
+    /// the file stays open and reading can go on after it.
+    pub fn is_recoverable(&self) -> bool {
+        matches!(
+            self,
+            PascalIoError::InvalidNumber
+                | PascalIoError::NumberOverflow
+                | PascalIoError::LineTooLong
+                | PascalIoError::LineLimitExceeded
+        )
+    }
+}
+
+impl fmt::Display for PascalIoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PascalIoError::NotFound => write!(f, "file not found"),
+            PascalIoError::PermissionDenied => write!(f, "permission denied"),
+            PascalIoError::IsADirectory => write!(f, "file is a directory"),
+            PascalIoError::NotOpen => write!(f, "file not in any mode"),
+            PascalIoError::WrongMode(mode) => write!(f, "file not in {} mode", mode),
+            PascalIoError::Eof => write!(f, "file eof reached"),
+            PascalIoError::UndefinedBufferVariable => {
+                write!(f, "file buffer variable value is undefined")
+            }
+            PascalIoError::DecodeError => write!(f, "file content cannot be decoded"),
+            PascalIoError::EncodeError(c) => write!(f, "character {:?} cannot be encoded", c),
+            PascalIoError::TruncatedUnit => write!(f, "file ends with an incomplete unit"),
+            PascalIoError::InvalidNumber => write!(f, "malformed number in text file"),
+            PascalIoError::NumberOverflow => write!(f, "number out of range"),
+            PascalIoError::NotSeekable => write!(f, "file does not support random access"),
+            PascalIoError::InvalidOption(option) => write!(f, "invalid file option `{}`", option),
+            PascalIoError::LineTooLong => write!(f, "line exceeds the length limit"),
+            PascalIoError::LineLimitExceeded => write!(f, "line exceeds the `/M` limit"),
+            PascalIoError::Io(e) => write!(f, "io error: {}", e),
+        }
+    }
+}
+
+impl Error for PascalIoError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PascalIoError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PascalIoError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::NotFound => PascalIoError::NotFound,
+            io::ErrorKind::PermissionDenied => PascalIoError::PermissionDenied,
+            io::ErrorKind::IsADirectory => PascalIoError::IsADirectory,
+            io::ErrorKind::InvalidData => PascalIoError::DecodeError,
+            _ => PascalIoError::Io(e),
+        }
+    }
+}
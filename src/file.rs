@@ -0,0 +1,137 @@
+use crate::stdio::{convert_crlf_to_lf, impl_text_file, unsupported_operation};
+use crate::{
+    BoxedBackend, FileBackend, FileState, FromBlob, PascalFile, PascalIoError, TextUnit, ToBlob,
+};
+use std::fmt;
+
+impl_text_file!(
+    TextFile<B>,
+    backend(B),
+    open_read(path) B::open_line_reader(path),
+    open_write(path) B::open_writer(path)
+);
+
+impl<U: TextUnit> TextFile<U> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<U: TextUnit, B: FileBackend> Default for TextFile<U, B> {
+    fn default() -> Self {
+        TextFile {
+            file_state: FileState::Undefined,
+            error_state: None,
+        }
+    }
+}
+
+pub struct ByteFile<B: FileBackend = BoxedBackend> {
+    file_state: FileState<u8, B>,
+    error_state: Option<PascalIoError>,
+}
+
+impl ByteFile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<B: FileBackend> Default for ByteFile<B> {
+    fn default() -> Self {
+        ByteFile {
+            file_state: FileState::Undefined,
+            error_state: None,
+        }
+    }
+}
+
+impl<B: FileBackend> fmt::Debug for ByteFile<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ByteFile")
+            .field("error_state", &self.error_state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<B: FileBackend> PascalFile for ByteFile<B> {
+    type Unit = u8;
+
+    type Backend = B;
+
+    fn is_text_file() -> bool {
+        false
+    }
+
+    fn is_eoln_unit(_: &u8) -> bool {
+        false
+    }
+
+    fn eoln_unit() -> u8 {
+        0
+    }
+
+    fn open_text_file_for_read(_: &str) -> Result<(B::LineReader, bool), PascalIoError> {
+        Err(unsupported_operation())
+    }
+
+    fn open_binary_file_for_read(path: &str) -> Result<B::Reader, PascalIoError> {
+        B::open_reader(path)
+    }
+
+    fn supports_random_access() -> bool {
+        true
+    }
+
+    fn open_binary_file_for_random_read(path: &str) -> Result<B::RandomReader, PascalIoError> {
+        B::open_random_reader(path)
+    }
+
+    fn open_file_for_write(path: &str) -> Result<B::Writer, PascalIoError> {
+        B::open_writer(path)
+    }
+
+    fn open_file_for_random_write(path: &str) -> Result<B::RandomWriter, PascalIoError> {
+        B::open_random_writer(path)
+    }
+
+    fn open_binary_file_for_update(path: &str) -> Result<B::Updater, PascalIoError> {
+        B::open_updater(path)
+    }
+
+    fn convert_line_bytes_crlf_to_lf(_: &mut Vec<u8>) {
+        unreachable!("byte files are never read in line mode")
+    }
+
+    fn convert_line_bytes_to_units(_: &[u8], _: &mut Vec<u8>) -> Result<(), PascalIoError> {
+        unreachable!("byte files are never read in line mode")
+    }
+
+    fn convert_blob_to_unit(input: &[u8]) -> u8 {
+        u8::from_blob(input)
+    }
+
+    fn convert_unit_to_blob(
+        data: u8,
+        f: &mut dyn for<'a> FnMut(&'a [u8]),
+    ) -> Result<(), PascalIoError> {
+        f(&data.to_blob());
+        Ok(())
+    }
+
+    fn file_state(&self) -> &FileState<u8, B> {
+        &self.file_state
+    }
+
+    fn file_state_mut(&mut self) -> &mut FileState<u8, B> {
+        &mut self.file_state
+    }
+
+    fn error_state(&self) -> Option<&PascalIoError> {
+        self.error_state.as_ref()
+    }
+
+    fn set_error_state(&mut self, error_state: Option<PascalIoError>) {
+        self.error_state = error_state;
+    }
+}
@@ -0,0 +1,207 @@
+use std::fmt;
+
+// Implementation-defined values allowed by ISO 7185 6.10.3.
+pub const DEFAULT_INTEGER_WIDTH: usize = 10;
+pub const DEFAULT_REAL_WIDTH: usize = 22;
+pub const DEFAULT_BOOLEAN_WIDTH: usize = 5;
+pub const DEFAULT_CHAR_WIDTH: usize = 1;
+pub const REAL_EXPONENT_DIGITS: usize = 2;
+
+pub trait PascalWrite {
+    fn fmt_pascal(
+        &self,
+        width: Option<usize>,
+        precision: Option<usize>,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result;
+}
+
+pub struct PascalFormatted<'a, T: ?Sized> {
+    value: &'a T,
+    width: Option<usize>,
+    precision: Option<usize>,
+}
+
+impl<'a, T: PascalWrite + ?Sized> PascalFormatted<'a, T> {
+    pub fn new(value: &'a T, width: Option<usize>, precision: Option<usize>) -> Self {
+        PascalFormatted {
+            value,
+            width,
+            precision,
+        }
+    }
+}
+
+impl<T: PascalWrite + ?Sized> fmt::Display for PascalFormatted<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt_pascal(self.width, self.precision, f)
+    }
+}
+
+fn write_right_justified(f: &mut fmt::Formatter<'_>, width: usize, s: &str) -> fmt::Result {
+    let len = s.chars().count();
+    for _ in len..width {
+        f.write_str(" ")?;
+    }
+    f.write_str(s)
+}
+
+fn write_string_field(f: &mut fmt::Formatter<'_>, width: usize, s: &str) -> fmt::Result {
+    match s.char_indices().nth(width) {
+        Some((truncate_at, _)) => f.write_str(&s[..truncate_at]),
+        None => write_right_justified(f, width, s),
+    }
+}
+
+fn write_real_field(
+    f: &mut fmt::Formatter<'_>,
+    value: f64,
+    width: Option<usize>,
+    precision: Option<usize>,
+) -> fmt::Result {
+    if !value.is_finite() {
+        let repr = if value.is_nan() {
+            "NaN"
+        } else if value > 0.0 {
+            "Inf"
+        } else {
+            "-Inf"
+        };
+        return write_right_justified(f, width.unwrap_or(DEFAULT_REAL_WIDTH), repr);
+    }
+    let repr = match precision {
+        Some(frac_digits) => {
+            let digits = format!("{:.*}", frac_digits, value.abs());
+            let nonzero = digits.bytes().any(|b| (b'1'..=b'9').contains(&b));
+            if value < 0.0 && nonzero {
+                format!("-{}", digits)
+            } else {
+                digits
+            }
+        }
+        None => {
+            let total_width = width.unwrap_or(DEFAULT_REAL_WIDTH);
+            let act_width = total_width.max(REAL_EXPONENT_DIGITS + 6);
+            let dec_places = act_width - REAL_EXPONENT_DIGITS - 5;
+            let digits = format!("{:.*e}", dec_places, value.abs());
+            let (mantissa, exponent) = digits.split_at(digits.find('e').unwrap());
+            let exponent: i32 = exponent[1..].parse().unwrap();
+            let nonzero = mantissa.bytes().any(|b| (b'1'..=b'9').contains(&b));
+            format!(
+                "{}{}E{}{:0width$}",
+                if value < 0.0 && nonzero { '-' } else { ' ' },
+                mantissa,
+                if exponent < 0 { '-' } else { '+' },
+                exponent.unsigned_abs(),
+                width = REAL_EXPONENT_DIGITS
+            )
+        }
+    };
+    write_right_justified(f, width.unwrap_or(0), &repr)
+}
+
+macro_rules! impl_pascal_write_for_integer {
+    ($($t:ty),*) => {
+        $(
+            impl PascalWrite for $t {
+                fn fmt_pascal(
+                    &self,
+                    width: Option<usize>,
+                    _: Option<usize>,
+                    f: &mut fmt::Formatter<'_>,
+                ) -> fmt::Result {
+                    let width = width.unwrap_or(DEFAULT_INTEGER_WIDTH);
+                    write_right_justified(f, width, &self.to_string())
+                }
+            }
+        )*
+    };
+}
+
+impl_pascal_write_for_integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+impl PascalWrite for f64 {
+    fn fmt_pascal(
+        &self,
+        width: Option<usize>,
+        precision: Option<usize>,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write_real_field(f, *self, width, precision)
+    }
+}
+
+impl PascalWrite for f32 {
+    fn fmt_pascal(
+        &self,
+        width: Option<usize>,
+        precision: Option<usize>,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write_real_field(f, f64::from(*self), width, precision)
+    }
+}
+
+impl PascalWrite for bool {
+    fn fmt_pascal(
+        &self,
+        width: Option<usize>,
+        _: Option<usize>,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        let repr = if *self { "TRUE" } else { "FALSE" };
+        write_string_field(f, width.unwrap_or(DEFAULT_BOOLEAN_WIDTH), repr)
+    }
+}
+
+impl PascalWrite for char {
+    fn fmt_pascal(
+        &self,
+        width: Option<usize>,
+        _: Option<usize>,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        let mut buf = [0u8; 4];
+        write_string_field(
+            f,
+            width.unwrap_or(DEFAULT_CHAR_WIDTH),
+            self.encode_utf8(&mut buf),
+        )
+    }
+}
+
+impl PascalWrite for str {
+    fn fmt_pascal(
+        &self,
+        width: Option<usize>,
+        _: Option<usize>,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match width {
+            Some(width) => write_string_field(f, width, self),
+            None => f.write_str(self),
+        }
+    }
+}
+
+impl PascalWrite for String {
+    fn fmt_pascal(
+        &self,
+        width: Option<usize>,
+        precision: Option<usize>,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        self.as_str().fmt_pascal(width, precision, f)
+    }
+}
+
+impl<T: PascalWrite + ?Sized> PascalWrite for &T {
+    fn fmt_pascal(
+        &self,
+        width: Option<usize>,
+        precision: Option<usize>,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        (**self).fmt_pascal(width, precision, f)
+    }
+}
@@ -1,31 +1,303 @@
+use std::convert::TryFrom;
 use std::fmt;
-use std::io::{self, Read, Write};
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
 
+mod backend;
+mod encoding;
+mod error;
+mod file;
+mod format;
+mod macros;
+mod options;
+mod stdio;
+#[cfg(test)]
+mod tests;
+
+pub use backend::{BoxedBackend, FileBackend, FsBackend};
+pub use encoding::{CodeTable, TextEncoding};
+pub use error::PascalIoError;
+pub use file::{ByteFile, TextFile};
+pub use format::{
+    PascalFormatted, PascalWrite, DEFAULT_BOOLEAN_WIDTH, DEFAULT_CHAR_WIDTH, DEFAULT_INTEGER_WIDTH,
+    DEFAULT_REAL_WIDTH, REAL_EXPONENT_DIGITS,
+};
+pub use options::{
+    FileOptions, LineEnding, LineOverflow, DEFAULT_BLOCK_SIZE, DEFAULT_WRITE_BUFFER_CAPACITY,
+};
+pub use stdio::{TextError, TextInput, TextOutput};
+
+#[cfg(feature = "derive")]
+pub use pascal_io_derive::PascalFile;
+
+// Appends the raw bytes of the next line to `buf`, up to and including the
+// first byte found in `delimiters`, but never more than `limit` bytes.
 pub trait ReadLine {
-    fn read_line(&mut self, buf: &mut String) -> io::Result<usize>;
+    fn read_line_bytes(
+        &mut self,
+        delimiters: &[u8],
+        limit: usize,
+        buf: &mut Vec<u8>,
+    ) -> io::Result<usize>;
+
+    fn is_at_end(&mut self) -> io::Result<bool>;
+}
+
+fn is_at_end<R: BufRead + ?Sized>(reader: &mut R) -> io::Result<bool> {
+    loop {
+        match reader.fill_buf() {
+            Ok(available) => return Ok(available.is_empty()),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn read_until_any<R: BufRead + ?Sized>(
+    reader: &mut R,
+    delimiters: &[u8],
+    limit: usize,
+    buf: &mut Vec<u8>,
+) -> io::Result<usize> {
+    let mut read_len = 0;
+    loop {
+        let available = match reader.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if available.is_empty() {
+            return Ok(read_len);
+        }
+        let available = &available[..available.len().min(limit - read_len)];
+        let (used_len, found) = match available.iter().position(|b| delimiters.contains(b)) {
+            Some(i) => (i + 1, true),
+            None => (available.len(), false),
+        };
+        buf.extend_from_slice(&available[..used_len]);
+        reader.consume(used_len);
+        read_len += used_len;
+        if found || read_len == limit {
+            return Ok(read_len);
+        }
+    }
 }
 
 impl ReadLine for io::Stdin {
-    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
-        io::Stdin::read_line(self, buf)
+    fn read_line_bytes(
+        &mut self,
+        delimiters: &[u8],
+        limit: usize,
+        buf: &mut Vec<u8>,
+    ) -> io::Result<usize> {
+        read_until_any(&mut self.lock(), delimiters, limit, buf)
+    }
+
+    fn is_at_end(&mut self) -> io::Result<bool> {
+        is_at_end(&mut self.lock())
     }
 }
 
 impl<R: io::Read> ReadLine for io::BufReader<R> {
-    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
-        <Self as io::BufRead>::read_line(self, buf)
+    fn read_line_bytes(
+        &mut self,
+        delimiters: &[u8],
+        limit: usize,
+        buf: &mut Vec<u8>,
+    ) -> io::Result<usize> {
+        read_until_any(self, delimiters, limit, buf)
+    }
+
+    fn is_at_end(&mut self) -> io::Result<bool> {
+        is_at_end(self)
+    }
+}
+
+impl<R: ReadLine + ?Sized> ReadLine for Box<R> {
+    fn read_line_bytes(
+        &mut self,
+        delimiters: &[u8],
+        limit: usize,
+        buf: &mut Vec<u8>,
+    ) -> io::Result<usize> {
+        (**self).read_line_bytes(delimiters, limit, buf)
+    }
+
+    fn is_at_end(&mut self) -> io::Result<bool> {
+        (**self).is_at_end()
     }
 }
 
 impl<T: AsRef<[u8]>> ReadLine for io::Cursor<T> {
-    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
-        <Self as io::BufRead>::read_line(self, buf)
+    fn read_line_bytes(
+        &mut self,
+        delimiters: &[u8],
+        limit: usize,
+        buf: &mut Vec<u8>,
+    ) -> io::Result<usize> {
+        read_until_any(self, delimiters, limit, buf)
+    }
+
+    fn is_at_end(&mut self) -> io::Result<bool> {
+        is_at_end(self)
+    }
+}
+
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+pub enum BlockReadTarget<B: FileBackend = BoxedBackend> {
+    Sequential(B::Reader),
+    Seekable(B::RandomReader),
+}
+
+impl<B: FileBackend> Read for BlockReadTarget<B> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            BlockReadTarget::Sequential(read_target) => read_target.read(buf),
+            BlockReadTarget::Seekable(read_target) => read_target.read(buf),
+        }
+    }
+}
+
+pub trait WriteSeek: Write + Seek {}
+
+impl<T: Write + Seek> WriteSeek for T {}
+
+pub trait ReadWriteSeek: Read + Write + Seek {}
+
+impl<T: Read + Write + Seek> ReadWriteSeek for T {}
+
+pub enum WriteTarget<B: FileBackend = BoxedBackend> {
+    Sequential(B::Writer),
+    Seekable(B::RandomWriter),
+}
+
+impl<B: FileBackend> WriteTarget<B> {
+    fn sync(&mut self) -> Result<(), PascalIoError> {
+        match self {
+            WriteTarget::Sequential(write_target) => B::sync_writer(write_target),
+            WriteTarget::Seekable(write_target) => B::sync_random_writer(write_target),
+        }
+    }
+}
+
+impl<B: FileBackend> Write for WriteTarget<B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            WriteTarget::Sequential(write_target) => write_target.write(buf),
+            WriteTarget::Seekable(write_target) => write_target.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            WriteTarget::Sequential(write_target) => write_target.flush(),
+            WriteTarget::Seekable(write_target) => write_target.flush(),
+        }
+    }
+}
+
+// Writes out the buffered bytes, keeping those the target did not take.
+fn flush_write_bytes_buffer<W: Write + ?Sized>(
+    write_target: &mut W,
+    write_bytes_buffer: &mut Vec<u8>,
+) -> io::Result<()> {
+    let mut written_len = 0;
+    let result = loop {
+        if written_len == write_bytes_buffer.len() {
+            break Ok(());
+        }
+        match write_target.write(&write_bytes_buffer[written_len..]) {
+            Ok(0) => break Err(io::ErrorKind::WriteZero.into()),
+            Ok(len) => written_len += len,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => break Err(e),
+        }
+    };
+    write_bytes_buffer.drain(..written_len);
+    result
+}
+
+struct PositionTrackingWriter<'a, B: FileBackend> {
+    write_target: &'a mut WriteTarget<B>,
+    write_bytes_buffer: &'a mut Vec<u8>,
+    write_position: &'a mut u64,
+    options: &'a FileOptions,
+}
+
+impl<B: FileBackend> PositionTrackingWriter<'_, B> {
+    fn write_text<F: PascalFile>(&mut self, args: fmt::Arguments<'_>) -> Result<(), PascalIoError> {
+        match self.options.encoding.unwrap_or_else(F::text_encoding) {
+            TextEncoding::Utf8 => self.write_fmt(args)?,
+            encoding => {
+                let mut bytes = vec![];
+                encoding.convert_str_to_bytes(&args.to_string(), &mut bytes)?;
+                self.write_all(&bytes)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_unit<F: PascalFile>(&mut self, unit: F::Unit) -> Result<(), PascalIoError> {
+        match self.options.encoding {
+            Some(encoding) if F::is_text_file() && encoding != F::text_encoding() => {
+                let mut text = String::new();
+                F::convert_unit_to_str(unit, &mut text)?;
+                let mut transcoded = vec![];
+                encoding.convert_str_to_bytes(&text, &mut transcoded)?;
+                self.write_all(&transcoded)?;
+            }
+            _ => {
+                let mut result = Ok(());
+                F::convert_unit_to_blob(unit, &mut |data| {
+                    if result.is_ok() {
+                        result = self.write_all(data);
+                    }
+                })?;
+                result?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_line_terminator(&mut self) -> io::Result<()> {
+        let terminator = self.options.line_ending.terminator();
+        self.write_all(terminator.as_bytes())
+    }
+}
+
+impl<B: FileBackend> Write for PositionTrackingWriter<'_, B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let capacity = self
+            .options
+            .write_buffer_capacity
+            .unwrap_or(DEFAULT_WRITE_BUFFER_CAPACITY);
+        if self.write_bytes_buffer.len() + buf.len() > capacity {
+            flush_write_bytes_buffer(self.write_target, self.write_bytes_buffer)?;
+        }
+        let written_len = if buf.len() >= capacity {
+            self.write_target.write(buf)?
+        } else {
+            self.write_bytes_buffer.extend_from_slice(buf);
+            buf.len()
+        };
+        *self.write_position += written_len as u64;
+        Ok(written_len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        flush_write_bytes_buffer(self.write_target, self.write_bytes_buffer)?;
+        self.write_target.flush()
     }
 }
 
+// The line and block buffers move between the states instead of being
+// dropped, so they are allocated once per file rather than once per line.
 pub enum LineBufferState<T> {
     UnknownState {
         initial_line: bool,
+        line_buffer: Vec<T>,
     },
     AfterReadLine {
         line_buffer: Vec<T>,
@@ -36,56 +308,202 @@ pub enum LineBufferState<T> {
 }
 
 pub enum BlockBufferState<T> {
-    UnknownState,
+    UnknownState {
+        bytes_block_buffer: Box<[u8]>,
+    },
     AfterReadBlock {
         bytes_block_buffer: Box<[u8]>,
         bytes_avail_length: usize,
         bytes_position: usize,
         bytes_buffer: Option<T>,
     },
-    Eof,
+    Eof {
+        bytes_block_buffer: Box<[u8]>,
+        // an incomplete unit at the end is yet to be reported by a bulk read.
+        truncated: bool,
+    },
 }
 
-pub enum FileState<T> {
+struct SpareBuffers<T> {
+    bytes_buffer: Vec<u8>,
+    line_buffer: Vec<T>,
+    block_buffer: Box<[u8]>,
+}
+
+#[derive(Default)]
+pub enum FileState<T, B: FileBackend = BoxedBackend> {
+    #[default]
     Undefined,
+    Failed,
     GenerationMode {
         write_buffer: Option<T>,
-        write_target: Box<dyn Write>,
+        write_target: WriteTarget<B>,
+        write_bytes_buffer: Vec<u8>,
+        write_position: u64,
+        options: FileOptions,
     },
     LineInspectionMode {
         read_line_buffer: LineBufferState<T>,
-        read_target: Box<dyn ReadLine>,
+        read_target: B::LineReader,
+        read_bytes_buffer: Vec<u8>,
         read_flag_extra_eoln_line: bool,
+        read_flag_after_cr: bool,
+        read_flag_line_truncated: bool,
+        options: FileOptions,
     },
     BlockInspectionMode {
         read_block_buffer: BlockBufferState<T>,
-        read_target: Box<dyn Read>,
+        read_target: BlockReadTarget<B>,
+        read_position: u64,
+        options: FileOptions,
+    },
+    // Reads go through the block buffer like `BlockInspectionMode`. The buffer
+    // variable holds the unit at `update_position` until it is assigned; `put`
+    // writes the assigned value there and `get` drops it, both then advance.
+    UpdateMode {
+        update_block_buffer: BlockBufferState<T>,
+        update_target: B::Updater,
+        update_position: u64,
+        write_buffer: Option<T>,
+        options: FileOptions,
     },
 }
 
-impl<T> Default for FileState<T> {
-    fn default() -> Self {
-        FileState::Undefined
+impl<T, B: FileBackend> fmt::Debug for FileState<T, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mode = match self {
+            FileState::Undefined => "Undefined",
+            FileState::Failed => "Failed",
+            FileState::GenerationMode { .. } => "GenerationMode",
+            FileState::LineInspectionMode { .. } => "LineInspectionMode",
+            FileState::BlockInspectionMode { .. } => "BlockInspectionMode",
+            FileState::UpdateMode { .. } => "UpdateMode",
+        };
+        f.write_str(mode)
+    }
+}
+
+// Output still buffered when the file is dropped without `close` is written
+// out here. As there is no caller left to tell, a failure panics in debug
+// builds, unless already unwinding or in error-state mode, and is only logged
+// to stderr otherwise.
+impl<T, B: FileBackend> Drop for FileState<T, B> {
+    fn drop(&mut self) {
+        if let FileState::GenerationMode {
+            write_target,
+            write_bytes_buffer,
+            options,
+            ..
+        } = self
+        {
+            if write_bytes_buffer.is_empty() {
+                return;
+            }
+            let result = flush_write_bytes_buffer(write_target, write_bytes_buffer)
+                .and_then(|()| write_target.flush());
+            if let Err(e) = result {
+                if cfg!(debug_assertions) && !options.error_state_mode && !std::thread::panicking()
+                {
+                    panic!("buffered output lost on drop: {}", e);
+                }
+                eprintln!("pascal_io: buffered output lost on drop: {}", e);
+            }
+        }
     }
 }
 
-impl<T> FileState<T> {
-    fn discard_buffer_variable_value_and_get_write_target(&mut self) -> &mut dyn Write {
+impl<T, B: FileBackend> FileState<T, B> {
+    pub fn options(&self) -> Option<&FileOptions> {
+        match self {
+            FileState::Undefined | FileState::Failed => None,
+            FileState::GenerationMode { options, .. }
+            | FileState::LineInspectionMode { options, .. }
+            | FileState::BlockInspectionMode { options, .. }
+            | FileState::UpdateMode { options, .. } => Some(options),
+        }
+    }
+
+    // Takes the buffers of the current mode for the next opening of the file.
+    fn take_spare_buffers(&mut self) -> SpareBuffers<T> {
+        let mut spare = SpareBuffers {
+            bytes_buffer: Vec::new(),
+            line_buffer: Vec::new(),
+            block_buffer: Box::default(),
+        };
+        match self {
+            FileState::LineInspectionMode {
+                read_line_buffer,
+                read_bytes_buffer,
+                ..
+            } => {
+                spare.bytes_buffer = core::mem::take(read_bytes_buffer);
+                if let LineBufferState::UnknownState { line_buffer, .. }
+                | LineBufferState::AfterReadLine { line_buffer, .. } = read_line_buffer
+                {
+                    spare.line_buffer = core::mem::take(line_buffer);
+                }
+            }
+            FileState::BlockInspectionMode {
+                read_block_buffer: block_buffer,
+                ..
+            }
+            | FileState::UpdateMode {
+                update_block_buffer: block_buffer,
+                ..
+            } => spare.block_buffer = block_buffer.take_block_buffer(),
+            _ => {}
+        }
+        spare
+    }
+
+    // Writes out what the target still takes and drops the rest, so replacing
+    // the state does not retry the write on drop.
+    fn flush_or_discard_write_bytes_buffer(&mut self) -> Result<(), PascalIoError> {
+        if let FileState::GenerationMode {
+            write_target,
+            write_bytes_buffer,
+            ..
+        } = self
+        {
+            let result = flush_write_bytes_buffer(write_target, write_bytes_buffer)
+                .and_then(|()| write_target.flush());
+            write_bytes_buffer.clear();
+            result?;
+        }
+        Ok(())
+    }
+
+    fn wrong_mode_error(&self, expected_mode: &'static str) -> PascalIoError {
+        match self {
+            FileState::Undefined | FileState::Failed => PascalIoError::NotOpen,
+            _ => PascalIoError::WrongMode(expected_mode),
+        }
+    }
+
+    fn discard_buffer_variable_value_and_get_write_target(
+        &mut self,
+    ) -> Result<PositionTrackingWriter<'_, B>, PascalIoError> {
         match self {
             FileState::GenerationMode {
                 write_buffer,
                 write_target,
+                write_bytes_buffer,
+                write_position,
+                options,
             } => {
                 *write_buffer = None;
-                write_target.as_mut()
-            }
-            _ => {
-                panic!("file not in generation mode!");
+                Ok(PositionTrackingWriter {
+                    write_target,
+                    write_bytes_buffer,
+                    write_position,
+                    options,
+                })
             }
+            _ => Err(self.wrong_mode_error("generation")),
         }
     }
 
-    fn refill<F>(&mut self)
+    fn refill<F>(&mut self) -> Result<(), PascalIoError>
     where
         F: PascalFile<Unit = T>,
     {
@@ -93,24 +511,91 @@ impl<T> FileState<T> {
             FileState::LineInspectionMode {
                 read_line_buffer,
                 read_target,
+                read_bytes_buffer: buf,
+                read_flag_after_cr,
+                read_flag_line_truncated,
+                options,
                 ..
             } => match read_line_buffer {
-                LineBufferState::UnknownState { initial_line } => {
+                LineBufferState::UnknownState {
+                    initial_line,
+                    line_buffer,
+                } => {
                     let initial_line = *initial_line;
-                    let mut buf = String::new();
-                    read_target.read_line(&mut buf).expect("read line failure");
+                    let delimiters: &[u8] = match options.line_ending {
+                        LineEnding::Auto => b"\n\r",
+                        LineEnding::Lf | LineEnding::CrLf => b"\n",
+                        LineEnding::Cr => b"\r",
+                    };
+                    let limit = options.max_line_length.unwrap_or(usize::MAX);
+                    buf.clear();
+                    read_target.read_line_bytes(delimiters, limit, buf)?;
+                    if *read_flag_after_cr {
+                        *read_flag_after_cr = false;
+                        // the rest of a `\r\n` ending the previous line.
+                        if buf == b"\n" {
+                            buf.clear();
+                            read_target.read_line_bytes(delimiters, limit, buf)?;
+                        }
+                    }
                     if initial_line && buf.is_empty() {
                         *read_line_buffer = LineBufferState::Eof;
-                        return;
+                        return Ok(());
                     }
-                    let mut line_chars = vec![];
-                    F::convert_line_string_crlf_to_lf(&mut buf);
-                    F::convert_line_string_to_units(&buf, &mut line_chars);
-                    let no_more = match line_chars.last() {
-                        Some(c) => !F::is_eoln_unit(c),
-                        None => true,
-                    };
-                    if no_more {
+                    let terminated = buf.last().is_some_and(|b| delimiters.contains(b));
+                    let mut no_more = !terminated;
+                    *read_flag_line_truncated = false;
+                    if !terminated && buf.len() == limit {
+                        match options.line_overflow {
+                            LineOverflow::Split => no_more = read_target.is_at_end()?,
+                            LineOverflow::Truncate | LineOverflow::Error => {
+                                // the rest is read past the kept part and cut off.
+                                loop {
+                                    buf.truncate(limit);
+                                    read_target.read_line_bytes(delimiters, limit, buf)?;
+                                    match buf[limit..].last() {
+                                        None => break,
+                                        Some(&b) if delimiters.contains(&b) => {
+                                            *read_flag_after_cr = b == b'\r'
+                                                && options.line_ending == LineEnding::Auto;
+                                            no_more = false;
+                                            break;
+                                        }
+                                        Some(_) => {}
+                                    }
+                                }
+                                buf.truncate(limit);
+                                if options.line_overflow == LineOverflow::Error {
+                                    // the line is skipped, reading goes on with the next one.
+                                    if no_more {
+                                        *read_line_buffer = LineBufferState::Eof;
+                                    }
+                                    return Err(PascalIoError::LineLimitExceeded);
+                                }
+                                *read_flag_line_truncated = true;
+                            }
+                        }
+                    }
+                    let mut line_chars = core::mem::take(line_buffer);
+                    line_chars.clear();
+                    match options.line_ending {
+                        LineEnding::Lf => {}
+                        LineEnding::CrLf => F::convert_line_bytes_crlf_to_lf(buf),
+                        LineEnding::Auto | LineEnding::Cr => {
+                            if let Some(last @ b'\r') = buf.last_mut() {
+                                *last = b'\n';
+                                *read_flag_after_cr = options.line_ending == LineEnding::Auto;
+                            }
+                        }
+                    }
+                    match options.encoding {
+                        Some(encoding) if encoding != F::text_encoding() => {
+                            let text = encoding.convert_bytes_to_string(buf)?;
+                            F::convert_str_to_units(&text, &mut line_chars)?;
+                        }
+                        _ => F::convert_line_bytes_to_units(buf, &mut line_chars)?,
+                    }
+                    if !line_chars.last().is_some_and(F::is_eoln_unit) {
                         line_chars.push(F::eoln_unit());
                     }
                     *read_line_buffer = LineBufferState::AfterReadLine {
@@ -118,279 +603,1019 @@ impl<T> FileState<T> {
                         line_position: 0,
                         line_no_more: no_more,
                     };
+                    Ok(())
                 }
                 _ => unreachable!(),
             },
             FileState::BlockInspectionMode {
                 read_block_buffer,
                 read_target,
+                ..
+            } => read_block_buffer.refill(read_target),
+            FileState::UpdateMode {
+                update_block_buffer,
+                update_target,
+                ..
+            } => update_block_buffer.refill(update_target),
+            _ => Err(self.wrong_mode_error("inspection")),
+        }
+    }
+}
+
+impl<T> BlockBufferState<T> {
+    // Reuses `spare` as the block when it already has the size wanted for
+    // `block_size`, which is rounded to hold at least one unit.
+    fn new(spare: Box<[u8]>, block_size: usize) -> Self {
+        let size_of_t = core::mem::size_of::<T>();
+        assert!(size_of_t > 0);
+        let dest_size = ((block_size / size_of_t) + 1) * size_of_t;
+        let bytes_block_buffer = if spare.len() == dest_size {
+            spare
+        } else {
+            vec![0u8; dest_size].into_boxed_slice()
+        };
+        BlockBufferState::UnknownState { bytes_block_buffer }
+    }
+
+    fn take_block_buffer(&mut self) -> Box<[u8]> {
+        match self {
+            BlockBufferState::UnknownState { bytes_block_buffer }
+            | BlockBufferState::AfterReadBlock {
+                bytes_block_buffer, ..
+            }
+            | BlockBufferState::Eof {
+                bytes_block_buffer, ..
+            } => core::mem::take(bytes_block_buffer),
+        }
+    }
+
+    fn refill<R: Read + ?Sized>(&mut self, read_target: &mut R) -> Result<(), PascalIoError> {
+        let size_of_t = core::mem::size_of::<T>();
+        assert!(size_of_t > 0);
+        if let BlockBufferState::UnknownState { bytes_block_buffer } = self {
+            let bytes_block_buffer = core::mem::take(bytes_block_buffer);
+            let dest_size = bytes_block_buffer.len();
+            *self = BlockBufferState::AfterReadBlock {
+                bytes_block_buffer,
+                bytes_avail_length: dest_size,
+                bytes_position: dest_size - size_of_t,
+                bytes_buffer: None,
+            }
+        }
+        match self {
+            BlockBufferState::AfterReadBlock {
+                bytes_block_buffer,
+                bytes_avail_length,
+                bytes_position,
+                bytes_buffer,
             } => {
-                const IDEAL_BUFSIZE: usize = 512;
-                let size_of_t = core::mem::size_of::<T>();
-                assert!(size_of_t > 0);
-                if matches!(read_block_buffer, BlockBufferState::UnknownState) {
-                    let dest_size = ((IDEAL_BUFSIZE / size_of_t) + 1) * size_of_t;
-                    *read_block_buffer = BlockBufferState::AfterReadBlock {
-                        bytes_block_buffer: vec![0u8; dest_size].into_boxed_slice(),
-                        bytes_avail_length: dest_size,
-                        bytes_position: dest_size - size_of_t,
-                        bytes_buffer: None,
+                let bytes_position_end = *bytes_position + size_of_t;
+                let mut remaining_range = bytes_position_end..*bytes_avail_length;
+                if remaining_range.start > 0 {
+                    if !remaining_range.is_empty() {
+                        bytes_block_buffer.copy_within(remaining_range.clone(), 0);
+                        remaining_range = 0..remaining_range.len();
+                    } else {
+                        remaining_range = 0..0;
                     }
                 }
-                match read_block_buffer {
-                    BlockBufferState::AfterReadBlock {
-                        bytes_block_buffer,
-                        bytes_avail_length,
-                        bytes_position,
-                        bytes_buffer,
-                    } => {
-                        let bytes_position_end = *bytes_position + size_of_t;
-                        let mut remaining_range = bytes_position_end..*bytes_avail_length;
-                        if remaining_range.start > 0 {
-                            if !remaining_range.is_empty() {
-                                bytes_block_buffer.copy_within(remaining_range.clone(), 0);
-                                remaining_range = 0..remaining_range.len();
-                            } else {
-                                remaining_range = 0..0;
-                            }
-                        }
-                        *bytes_avail_length = remaining_range.end;
-                        *bytes_position = 0;
-                        *bytes_buffer = None;
-                        while *bytes_avail_length < size_of_t {
-                            let fillable_range = *bytes_avail_length..bytes_block_buffer.len();
-                            let newly_read_len = read_target
-                                .read(&mut bytes_block_buffer[fillable_range])
-                                .expect("read block failure");
-                            if newly_read_len == 0 {
-                                *read_block_buffer = BlockBufferState::Eof;
-                                return;
-                            }
-                            *bytes_avail_length += newly_read_len;
+                *bytes_avail_length = remaining_range.end;
+                *bytes_position = 0;
+                *bytes_buffer = None;
+                while *bytes_avail_length < size_of_t {
+                    let fillable_range = *bytes_avail_length..bytes_block_buffer.len();
+                    let newly_read_len =
+                        read_target.read(&mut bytes_block_buffer[fillable_range])?;
+                    if newly_read_len == 0 {
+                        let truncated = *bytes_avail_length > 0;
+                        let bytes_block_buffer = core::mem::take(bytes_block_buffer);
+                        *self = BlockBufferState::Eof {
+                            bytes_block_buffer,
+                            truncated: false,
+                        };
+                        if truncated {
+                            return Err(PascalIoError::TruncatedUnit);
                         }
+                        return Ok(());
                     }
-                    _ => unreachable!(),
+                    *bytes_avail_length += newly_read_len;
                 }
+                Ok(())
             }
-            _ => {
-                panic!("file not in inspection mode!");
-            }
+            _ => unreachable!(),
         }
     }
-}
-
-pub trait PascalFile {
-    type Unit;
-
-    fn is_text_file() -> bool;
-
-    fn is_eoln_unit(unit: &Self::Unit) -> bool;
-
-    fn eoln_unit() -> Self::Unit;
-
-    fn open_text_file_for_read(path: &str) -> Result<(Box<dyn ReadLine>, bool), usize>;
-
-    fn open_binary_file_for_read(path: &str) -> Result<Box<dyn Read>, usize>;
-
-    fn open_file_for_write(path: &str) -> Result<Box<dyn Write>, usize>;
-
-    fn convert_line_string_crlf_to_lf(input: &mut String);
-
-    fn convert_line_string_to_units(input: &str, units: &mut Vec<Self::Unit>);
-
-    fn convert_blob_to_unit(input: &[u8]) -> Self::Unit;
-
-    fn convert_unit_to_blob(data: Self::Unit, f: &mut dyn for<'a> FnMut(&'a [u8]));
-
-    fn file_state(&self) -> &FileState<Self::Unit>;
 
-    fn file_state_mut(&mut self) -> &mut FileState<Self::Unit>;
-
-    fn error_state(&self) -> usize;
-
-    fn set_error_state(&mut self, error_state: usize);
-}
-
-pub trait FromBlob {
-    fn from_blob(data: &[u8]) -> Self;
-}
-pub trait ToBlob {
-    type BlobType: core::borrow::Borrow<[u8]>;
-
-    fn to_blob(&self) -> Self::BlobType;
-}
-
-pub fn reset<F: PascalFile + fmt::Debug, P: Into<String> + fmt::Debug>(
-    file: &mut F,
-    path: P,
-    _options: &str,
-) {
-    let path = path.into();
-    if F::is_text_file() {
-        match F::open_text_file_for_read(&path) {
-            Ok((read_target, is_terminal)) => {
-                if is_terminal {
-                    *file.file_state_mut() = FileState::LineInspectionMode {
-                        read_target,
-                        read_line_buffer: LineBufferState::AfterReadLine {
-                            line_buffer: vec![F::eoln_unit()],
-                            line_position: 0,
-                            line_no_more: false,
-                        },
-                        read_flag_extra_eoln_line: true,
-                    };
-                } else {
-                    *file.file_state_mut() = FileState::LineInspectionMode {
-                        read_target,
-                        read_line_buffer: LineBufferState::UnknownState { initial_line: true },
-                        read_flag_extra_eoln_line: false,
-                    };
+    // Like `refill`, but once `read_len` units were read an incomplete unit at
+    // the end is left for the next bulk read to report, keeping the count.
+    fn refill_for_bulk_read<R: Read + ?Sized>(
+        &mut self,
+        read_target: &mut R,
+        read_len: usize,
+    ) -> Result<(), PascalIoError> {
+        match self.refill(read_target) {
+            Err(PascalIoError::TruncatedUnit) if read_len > 0 => {
+                if let BlockBufferState::Eof { truncated, .. } = self {
+                    *truncated = true;
                 }
-                file.set_error_state(0);
-            }
-            Err(e) => {
-                *file.file_state_mut() = FileState::Undefined;
-                file.set_error_state(e);
+                Ok(())
             }
+            result => result,
         }
-    } else {
-        match F::open_binary_file_for_read(&path) {
-            Ok(read_target) => {
-                *file.file_state_mut() = FileState::BlockInspectionMode {
-                    read_target,
-                    read_block_buffer: BlockBufferState::UnknownState,
-                };
-                file.set_error_state(0);
-            }
-            Err(e) => {
-                *file.file_state_mut() = FileState::Undefined;
-                file.set_error_state(e);
+    }
+
+    fn get<R: Read + ?Sized>(
+        &mut self,
+        read_target: &mut R,
+        position: &mut u64,
+    ) -> Result<(), PascalIoError> {
+        loop {
+            match self {
+                BlockBufferState::Eof { .. } => return Err(PascalIoError::Eof),
+                // After `reset` or `seek` the unit at `position` is not loaded yet, so
+                // load it and then move past it.
+                BlockBufferState::UnknownState { .. } => {
+                    self.refill(read_target)?;
+                    continue;
+                }
+                BlockBufferState::AfterReadBlock {
+                    bytes_avail_length,
+                    bytes_position,
+                    bytes_buffer,
+                    ..
+                } => {
+                    let size_of_t = core::mem::size_of::<T>();
+                    assert!(size_of_t > 0);
+                    *position += 1;
+                    let bytes_position_end = *bytes_position + size_of_t;
+                    let new_bytes_position_end = bytes_position_end + size_of_t;
+                    if new_bytes_position_end > *bytes_avail_length {
+                        return self.refill(read_target);
+                    }
+                    *bytes_buffer = None;
+                    *bytes_position = bytes_position_end;
+                    return Ok(());
+                }
             }
         }
     }
-}
 
-pub fn rewrite<F: PascalFile, P: Into<String>>(file: &mut F, path: P, _options: &str) {
-    let path = path.into();
-    match F::open_file_for_write(&path) {
-        Ok(write_target) => {
-            *file.file_state_mut() = FileState::GenerationMode {
-                write_target,
-                write_buffer: None,
-            };
-            file.set_error_state(0);
-        }
-        Err(e) => {
-            *file.file_state_mut() = FileState::Undefined;
-            file.set_error_state(e);
+    fn buffer_variable<F, R>(&mut self, read_target: &mut R) -> Result<T, PascalIoError>
+    where
+        F: PascalFile<Unit = T>,
+        R: Read + ?Sized,
+        T: Clone,
+    {
+        loop {
+            match self {
+                BlockBufferState::Eof { .. } => return Err(PascalIoError::Eof),
+                BlockBufferState::UnknownState { .. } => {
+                    self.refill(read_target)?;
+                    continue;
+                }
+                BlockBufferState::AfterReadBlock {
+                    bytes_block_buffer,
+                    bytes_buffer,
+                    bytes_position,
+                    ..
+                } => match bytes_buffer {
+                    None => {
+                        let size_of_t = core::mem::size_of::<T>();
+                        assert!(size_of_t > 0);
+                        let bytes_position_end = *bytes_position + size_of_t;
+                        let v = F::convert_blob_to_unit(
+                            &bytes_block_buffer[*bytes_position..bytes_position_end],
+                        );
+                        *bytes_buffer = Some(v.clone());
+                        return Ok(v);
+                    }
+                    Some(v) => return Ok(v.clone()),
+                },
+            }
+        }
+    }
+
+    fn eof<R: Read + ?Sized>(&mut self, read_target: &mut R) -> Result<bool, PascalIoError> {
+        if let BlockBufferState::UnknownState { .. } = self {
+            self.refill(read_target)?;
+        }
+        Ok(matches!(self, BlockBufferState::Eof { .. }))
+    }
+
+    // Converts units straight out of the block, stopping early at the end of
+    // the file. Leaves the state as that many `get`s would.
+    fn read_units<F, R>(
+        &mut self,
+        read_target: &mut R,
+        position: &mut u64,
+        units: &mut [T],
+    ) -> Result<usize, PascalIoError>
+    where
+        F: PascalFile<Unit = T>,
+        R: Read + ?Sized,
+    {
+        let size_of_t = core::mem::size_of::<T>();
+        assert!(size_of_t > 0);
+        let mut read_len = 0;
+        while read_len < units.len() {
+            match self {
+                BlockBufferState::Eof { truncated, .. } => {
+                    if read_len == 0 && core::mem::take(truncated) {
+                        return Err(PascalIoError::TruncatedUnit);
+                    }
+                    break;
+                }
+                BlockBufferState::UnknownState { .. } => {
+                    self.refill_for_bulk_read(read_target, read_len)?
+                }
+                BlockBufferState::AfterReadBlock {
+                    bytes_block_buffer,
+                    bytes_avail_length,
+                    bytes_position,
+                    bytes_buffer,
+                } => {
+                    let blobs = bytes_block_buffer[*bytes_position..*bytes_avail_length]
+                        .chunks_exact(size_of_t);
+                    let mut copied_len = 0;
+                    for (unit, blob) in units[read_len..].iter_mut().zip(blobs) {
+                        *unit = F::convert_blob_to_unit(blob);
+                        copied_len += 1;
+                    }
+                    read_len += copied_len;
+                    *position += copied_len as u64;
+                    *bytes_buffer = None;
+                    *bytes_position += copied_len * size_of_t;
+                    if *bytes_position + size_of_t > *bytes_avail_length {
+                        // `refill` keeps what follows the unit at `bytes_position`.
+                        *bytes_position -= size_of_t;
+                        self.refill_for_bulk_read(read_target, read_len)?;
+                    }
+                }
+            }
         }
+        Ok(read_len)
+    }
+
+    // Copies the bytes of whole units, as many as `bytes` holds, and returns
+    // the count copied, short only at the end of the file.
+    fn read_bytes<R: Read + ?Sized>(
+        &mut self,
+        read_target: &mut R,
+        position: &mut u64,
+        bytes: &mut [u8],
+    ) -> Result<usize, PascalIoError> {
+        let size_of_t = core::mem::size_of::<T>();
+        assert!(size_of_t > 0 && bytes.len().is_multiple_of(size_of_t));
+        let mut read_len = 0;
+        while read_len < bytes.len() {
+            match self {
+                BlockBufferState::Eof { truncated, .. } => {
+                    if read_len == 0 && core::mem::take(truncated) {
+                        return Err(PascalIoError::TruncatedUnit);
+                    }
+                    break;
+                }
+                BlockBufferState::UnknownState { .. } => {
+                    self.refill_for_bulk_read(read_target, read_len)?
+                }
+                BlockBufferState::AfterReadBlock {
+                    bytes_block_buffer,
+                    bytes_avail_length,
+                    bytes_position,
+                    bytes_buffer,
+                } => {
+                    let avail_len = (*bytes_avail_length - *bytes_position) / size_of_t * size_of_t;
+                    let copied_len = avail_len.min(bytes.len() - read_len);
+                    bytes[read_len..read_len + copied_len].copy_from_slice(
+                        &bytes_block_buffer[*bytes_position..*bytes_position + copied_len],
+                    );
+                    read_len += copied_len;
+                    *position += (copied_len / size_of_t) as u64;
+                    *bytes_buffer = None;
+                    *bytes_position += copied_len;
+                    if *bytes_position + size_of_t > *bytes_avail_length {
+                        *bytes_position -= size_of_t;
+                        self.refill_for_bulk_read(read_target, read_len)?;
+                    }
+                }
+            }
+        }
+        Ok(read_len)
+    }
+
+    // `read_target` must be positioned right after the buffered block, or at
+    // `position` when nothing is buffered.
+    fn seek<R: Seek + ?Sized>(
+        &mut self,
+        read_target: &mut R,
+        position: &mut u64,
+        unit_index: u64,
+    ) -> Result<(), PascalIoError> {
+        let size_of_t = core::mem::size_of::<T>();
+        assert!(size_of_t > 0);
+        if let BlockBufferState::AfterReadBlock {
+            bytes_avail_length,
+            bytes_position,
+            bytes_buffer,
+            ..
+        } = self
+        {
+            let buffered_first = *position - (*bytes_position / size_of_t) as u64;
+            let buffered_count = (*bytes_avail_length / size_of_t) as u64;
+            if (buffered_first..buffered_first + buffered_count).contains(&unit_index) {
+                *bytes_position = (unit_index - buffered_first) as usize * size_of_t;
+                *bytes_buffer = None;
+                *position = unit_index;
+                return Ok(());
+            }
+        }
+        let byte_offset = unit_index
+            .checked_mul(size_of_t as u64)
+            .ok_or(PascalIoError::NumberOverflow)?;
+        let bytes_block_buffer = self.take_block_buffer();
+        *self = BlockBufferState::UnknownState { bytes_block_buffer };
+        *position = unit_index;
+        read_target.seek(SeekFrom::Start(byte_offset))?;
+        Ok(())
+    }
+
+    // Writes the units in `bytes` over the file from `position` on with a
+    // single write, and leaves the file at the unit after them, as that many
+    // `put` calls would.
+    fn write_in_place<W: Write + Seek + ?Sized>(
+        &mut self,
+        update_target: &mut W,
+        position: &mut u64,
+        bytes: &[u8],
+    ) -> Result<(), PascalIoError> {
+        let size_of_t = core::mem::size_of::<T>();
+        assert!(size_of_t > 0);
+        let byte_offset = *position * size_of_t as u64;
+        update_target.seek(SeekFrom::Start(byte_offset))?;
+        update_target.write_all(bytes)?;
+        let written_count = (bytes.len() / size_of_t) as u64;
+        if let BlockBufferState::AfterReadBlock {
+            bytes_block_buffer,
+            bytes_avail_length,
+            bytes_position,
+            bytes_buffer,
+        } = self
+        {
+            // the buffered block is patched when the next unit is still in it,
+            // and left for a refill from the end of the written bytes otherwise.
+            let new_bytes_position = *bytes_position + bytes.len();
+            if new_bytes_position + size_of_t <= *bytes_avail_length {
+                bytes_block_buffer[*bytes_position..new_bytes_position].copy_from_slice(bytes);
+                let block_end = byte_offset - *bytes_position as u64 + *bytes_avail_length as u64;
+                *bytes_position = new_bytes_position;
+                *bytes_buffer = None;
+                *position += written_count;
+                update_target.seek(SeekFrom::Start(block_end))?;
+                return Ok(());
+            }
+            let bytes_block_buffer = self.take_block_buffer();
+            *self = BlockBufferState::UnknownState { bytes_block_buffer };
+        }
+        *position += written_count;
+        Ok(())
     }
 }
 
-pub fn buffer_variable_assign<F: PascalFile>(file: &mut F, value: F::Unit) {
-    match file.file_state_mut() {
-        FileState::GenerationMode { write_buffer, .. } => {
-            *write_buffer = Some(value);
+pub trait PascalFile {
+    type Unit;
+
+    type Backend: FileBackend;
+
+    fn is_text_file() -> bool;
+
+    fn is_eoln_unit(unit: &Self::Unit) -> bool;
+
+    fn eoln_unit() -> Self::Unit;
+
+    fn open_text_file_for_read(
+        path: &str,
+    ) -> Result<(<Self::Backend as FileBackend>::LineReader, bool), PascalIoError>;
+
+    fn open_binary_file_for_read(
+        path: &str,
+    ) -> Result<<Self::Backend as FileBackend>::Reader, PascalIoError>;
+
+    // Types returning true here must provide both `open_binary_file_for_random_read`
+    // and `open_file_for_random_write`.
+    fn supports_random_access() -> bool {
+        false
+    }
+
+    fn open_binary_file_for_random_read(
+        _path: &str,
+    ) -> Result<<Self::Backend as FileBackend>::RandomReader, PascalIoError> {
+        Err(PascalIoError::NotSeekable)
+    }
+
+    fn open_file_for_write(
+        path: &str,
+    ) -> Result<<Self::Backend as FileBackend>::Writer, PascalIoError>;
+
+    fn open_file_for_random_write(
+        _path: &str,
+    ) -> Result<<Self::Backend as FileBackend>::RandomWriter, PascalIoError> {
+        Err(PascalIoError::NotSeekable)
+    }
+
+    fn open_binary_file_for_update(
+        _path: &str,
+    ) -> Result<<Self::Backend as FileBackend>::Updater, PascalIoError> {
+        Err(PascalIoError::NotSeekable)
+    }
+
+    // Returns the write target along with the current length of the file.
+    fn open_file_for_append(
+        path: &str,
+    ) -> Result<(<Self::Backend as FileBackend>::Writer, u64), PascalIoError> {
+        <Self::Backend as FileBackend>::open_appender(path)
+    }
+
+    fn convert_line_bytes_crlf_to_lf(input: &mut Vec<u8>);
+
+    // Must accept any bytes, the input is not guaranteed to be valid UTF-8.
+    fn convert_line_bytes_to_units(
+        input: &[u8],
+        units: &mut Vec<Self::Unit>,
+    ) -> Result<(), PascalIoError>;
+
+    fn convert_blob_to_unit(input: &[u8]) -> Self::Unit;
+
+    fn convert_unit_to_blob(
+        data: Self::Unit,
+        f: &mut dyn for<'a> FnMut(&'a [u8]),
+    ) -> Result<(), PascalIoError>;
+
+    // Encoding of the bytes passed to and from the conversion hooks of text
+    // files, also used for text written with `write` and `write_ln`.
+    fn text_encoding() -> TextEncoding {
+        TextEncoding::Utf8
+    }
+
+    // Convert between units and the text of files opened with `/E`.
+    fn convert_str_to_units(input: &str, units: &mut Vec<Self::Unit>) -> Result<(), PascalIoError> {
+        let mut bytes = vec![];
+        Self::text_encoding().convert_str_to_bytes(input, &mut bytes)?;
+        Self::convert_line_bytes_to_units(&bytes, units)
+    }
+
+    fn convert_unit_to_str(data: Self::Unit, text: &mut String) -> Result<(), PascalIoError> {
+        let mut bytes = vec![];
+        Self::convert_unit_to_blob(data, &mut |data| bytes.extend_from_slice(data))?;
+        text.push_str(&Self::text_encoding().convert_bytes_to_string(&bytes)?);
+        Ok(())
+    }
+
+    fn file_state(&self) -> &FileState<Self::Unit, Self::Backend>;
+
+    fn file_state_mut(&mut self) -> &mut FileState<Self::Unit, Self::Backend>;
+
+    fn error_state(&self) -> Option<&PascalIoError>;
+
+    fn set_error_state(&mut self, error_state: Option<PascalIoError>);
+
+    // When enabled, failures in the non-`try_` procedures are recorded for
+    // `erstat` instead of panicking. They leave the file in `FileState::Failed`,
+    // except for recoverable failures in the data read.
+    fn error_state_mode(&self) -> bool {
+        false
+    }
+}
+
+pub trait TextUnit: Copy {
+    fn to_char(self) -> char;
+
+    fn from_char(c: char) -> Option<Self>;
+
+    // The unit for `c` in text of `encoding`, `?` when there is none.
+    fn from_encoded_char(_encoding: TextEncoding, c: char) -> Result<Self, PascalIoError> {
+        Ok(Self::from_char(c).unwrap_or_else(|| Self::from_char('?').unwrap()))
+    }
+
+    // The character `self` stands for in text of `encoding`.
+    fn to_encoded_char(self, _encoding: TextEncoding) -> Result<char, PascalIoError> {
+        Ok(self.to_char())
+    }
+
+    fn eoln_unit() -> Self;
+
+    fn convert_bytes_to_units(input: &[u8], units: &mut Vec<Self>);
+
+    fn convert_unit_to_bytes(self, f: &mut dyn for<'a> FnMut(&'a [u8]));
+}
+
+pub trait FromBlob {
+    fn from_blob(data: &[u8]) -> Self;
+}
+pub trait ToBlob {
+    type BlobType: core::borrow::Borrow<[u8]>;
+
+    fn to_blob(&self) -> Self::BlobType;
+}
+
+pub fn reset<F: PascalFile + fmt::Debug, P: Into<String> + fmt::Debug>(
+    file: &mut F,
+    path: P,
+    options: &str,
+) {
+    let path = path.into();
+    let options = match parse_options(file, options) {
+        Ok(options) => options,
+        Err(e) => return file_open_failed(file, &FileOptions::default(), e),
+    };
+    let flush_error = flush_before_reopen(file);
+    let spare = file.file_state_mut().take_spare_buffers();
+    if F::is_text_file() {
+        match F::open_text_file_for_read(&path) {
+            Ok((read_target, is_terminal)) => {
+                let mut line_buffer = spare.line_buffer;
+                if options.interactive.unwrap_or(is_terminal) {
+                    line_buffer.clear();
+                    line_buffer.push(F::eoln_unit());
+                    *file.file_state_mut() = FileState::LineInspectionMode {
+                        read_target,
+                        read_bytes_buffer: spare.bytes_buffer,
+                        read_line_buffer: LineBufferState::AfterReadLine {
+                            line_buffer,
+                            line_position: 0,
+                            line_no_more: false,
+                        },
+                        read_flag_extra_eoln_line: true,
+                        read_flag_after_cr: false,
+                        read_flag_line_truncated: false,
+                        options,
+                    };
+                } else {
+                    *file.file_state_mut() = FileState::LineInspectionMode {
+                        read_target,
+                        read_bytes_buffer: spare.bytes_buffer,
+                        read_line_buffer: LineBufferState::UnknownState {
+                            initial_line: true,
+                            line_buffer,
+                        },
+                        read_flag_extra_eoln_line: false,
+                        read_flag_after_cr: false,
+                        read_flag_line_truncated: false,
+                        options,
+                    };
+                }
+                file.set_error_state(flush_error);
+            }
+            Err(e) => {
+                file_open_failed(file, &options, e);
+            }
         }
-        _ => {
-            panic!("file not in generation mode!");
+    } else {
+        let read_target = if F::supports_random_access() {
+            F::open_binary_file_for_random_read(&path).map(BlockReadTarget::Seekable)
+        } else {
+            F::open_binary_file_for_read(&path).map(BlockReadTarget::Sequential)
+        };
+        match read_target {
+            Ok(read_target) => {
+                let block_size = options.block_size.unwrap_or(DEFAULT_BLOCK_SIZE);
+                *file.file_state_mut() = FileState::BlockInspectionMode {
+                    read_target,
+                    read_block_buffer: BlockBufferState::new(spare.block_buffer, block_size),
+                    read_position: 0,
+                    options,
+                };
+                file.set_error_state(flush_error);
+            }
+            Err(e) => {
+                file_open_failed(file, &options, e);
+            }
         }
     }
 }
 
-pub fn put<F: PascalFile>(file: &mut F) {
-    match file.file_state_mut() {
+pub fn rewrite<F: PascalFile, P: Into<String>>(file: &mut F, path: P, options: &str) {
+    let path = path.into();
+    let options = match parse_options(file, options) {
+        Ok(options) => options,
+        Err(e) => return file_open_failed(file, &FileOptions::default(), e),
+    };
+    let flush_error = flush_before_reopen(file);
+    if options.append {
+        return open_for_append(file, &path, options, flush_error);
+    }
+    let write_target = if F::supports_random_access() {
+        F::open_file_for_random_write(&path).map(WriteTarget::Seekable)
+    } else {
+        F::open_file_for_write(&path).map(WriteTarget::Sequential)
+    };
+    match write_target {
+        Ok(write_target) => {
+            *file.file_state_mut() = FileState::GenerationMode {
+                write_target,
+                write_bytes_buffer: write_bytes_buffer(&options),
+                write_buffer: None,
+                write_position: 0,
+                options,
+            };
+            file.set_error_state(flush_error);
+        }
+        Err(e) => {
+            file_open_failed(file, &options, e);
+        }
+    }
+}
+
+pub fn extend<F: PascalFile, P: Into<String>>(file: &mut F, path: P, options: &str) {
+    let path = path.into();
+    match parse_options(file, options) {
+        Ok(options) => {
+            let flush_error = flush_before_reopen(file);
+            open_for_append(file, &path, options, flush_error)
+        }
+        Err(e) => file_open_failed(file, &FileOptions::default(), e),
+    }
+}
+
+// The options of an opening, in error-state mode as well when the file type
+// asks for it.
+fn parse_options<F: PascalFile>(file: &F, options: &str) -> Result<FileOptions, PascalIoError> {
+    let mut options = options.parse::<FileOptions>()?;
+    options.error_state_mode |= file.error_state_mode();
+    Ok(options)
+}
+
+// Writes out the output still buffered from the previous opening, which
+// replacing the state would otherwise leave to `Drop`. A failure panics,
+// unless the previous opening was in error-state mode; it is then returned
+// to be recorded for `erstat` once the file is open again.
+fn flush_before_reopen<F: PascalFile>(file: &mut F) -> Option<PascalIoError> {
+    let error_state_mode = file.error_state_mode()
+        || file
+            .file_state()
+            .options()
+            .is_some_and(|o| o.error_state_mode);
+    match file.file_state_mut().flush_or_discard_write_bytes_buffer() {
+        Ok(()) => None,
+        Err(e) if error_state_mode => Some(e),
+        Err(e) => panic!("{}", e),
+    }
+}
+
+fn write_bytes_buffer(options: &FileOptions) -> Vec<u8> {
+    Vec::with_capacity(
+        options
+            .write_buffer_capacity
+            .unwrap_or(DEFAULT_WRITE_BUFFER_CAPACITY),
+    )
+}
+
+fn open_for_append<F: PascalFile>(
+    file: &mut F,
+    path: &str,
+    options: FileOptions,
+    flush_error: Option<PascalIoError>,
+) {
+    match F::open_file_for_append(path) {
+        Ok((write_target, file_len)) => {
+            *file.file_state_mut() = FileState::GenerationMode {
+                write_target: WriteTarget::Sequential(write_target),
+                write_bytes_buffer: write_bytes_buffer(&options),
+                write_buffer: None,
+                write_position: file_len,
+                options,
+            };
+            file.set_error_state(flush_error);
+        }
+        Err(e) => {
+            file_open_failed(file, &options, e);
+        }
+    }
+}
+
+// Opens an existing binary file for reading and writing in place.
+pub fn update<F: PascalFile, P: Into<String>>(file: &mut F, path: P, options: &str) {
+    let path = path.into();
+    let options = match parse_options(file, options) {
+        Ok(options) => options,
+        Err(e) => return file_open_failed(file, &FileOptions::default(), e),
+    };
+    let flush_error = flush_before_reopen(file);
+    match F::open_binary_file_for_update(&path) {
+        Ok(update_target) => {
+            let spare = file.file_state_mut().take_spare_buffers();
+            let block_size = options.block_size.unwrap_or(DEFAULT_BLOCK_SIZE);
+            *file.file_state_mut() = FileState::UpdateMode {
+                update_target,
+                update_block_buffer: BlockBufferState::new(spare.block_buffer, block_size),
+                update_position: 0,
+                write_buffer: None,
+                options,
+            };
+            file.set_error_state(flush_error);
+        }
+        Err(e) => {
+            file_open_failed(file, &options, e);
+        }
+    }
+}
+
+fn file_open_failed<F: PascalFile>(file: &mut F, options: &FileOptions, e: PascalIoError) {
+    let _ = file.file_state_mut().flush_or_discard_write_bytes_buffer();
+    *file.file_state_mut() = if options.error_state_mode || file.error_state_mode() {
+        FileState::Failed
+    } else {
+        FileState::Undefined
+    };
+    file.set_error_state(Some(e));
+}
+
+fn unwrap_or_record<F: PascalFile, T>(
+    file: &mut F,
+    result: Result<T, PascalIoError>,
+    fallback: T,
+) -> T {
+    match result {
+        Ok(v) => v,
+        Err(e) => {
+            match file.file_state() {
+                // the failure that brought the file here is already recorded.
+                FileState::Failed => {}
+                state
+                    if file.error_state_mode()
+                        || state.options().is_some_and(|o| o.error_state_mode) =>
+                {
+                    if !e.is_recoverable() {
+                        let _ = file.file_state_mut().flush_or_discard_write_bytes_buffer();
+                        *file.file_state_mut() = FileState::Failed;
+                    }
+                    file.set_error_state(Some(e));
+                }
+                _ => panic!("{}", e),
+            }
+            fallback
+        }
+    }
+}
+
+pub fn try_buffer_variable_assign<F: PascalFile>(
+    file: &mut F,
+    value: F::Unit,
+) -> Result<(), PascalIoError> {
+    let file_state = file.file_state_mut();
+    match file_state {
+        FileState::GenerationMode { write_buffer, .. }
+        | FileState::UpdateMode { write_buffer, .. } => {
+            *write_buffer = Some(value);
+            Ok(())
+        }
+        _ => Err(file_state.wrong_mode_error("generation")),
+    }
+}
+
+pub fn buffer_variable_assign<F: PascalFile>(file: &mut F, value: F::Unit) {
+    let result = try_buffer_variable_assign(file, value);
+    unwrap_or_record(file, result, ())
+}
+
+pub fn try_put<F: PascalFile>(file: &mut F) -> Result<(), PascalIoError> {
+    let file_state = file.file_state_mut();
+    match file_state {
         FileState::GenerationMode {
             write_target,
+            write_bytes_buffer,
+            write_buffer,
+            write_position,
+            options,
+        } => {
+            let caret_value = write_buffer
+                .take()
+                .ok_or(PascalIoError::UndefinedBufferVariable)?;
+            let mut write_target = PositionTrackingWriter {
+                write_target,
+                write_bytes_buffer,
+                write_position,
+                options,
+            };
+            write_target.write_unit::<F>(caret_value)
+        }
+        FileState::UpdateMode {
+            update_block_buffer,
+            update_target,
+            update_position,
             write_buffer,
+            ..
         } => {
             let caret_value = write_buffer
                 .take()
-                .expect("file buffer variable value is undefined!");
+                .ok_or(PascalIoError::UndefinedBufferVariable)?;
+            let size_of_t = core::mem::size_of::<F::Unit>();
+            assert!(size_of_t > 0);
+            let byte_offset = *update_position * size_of_t as u64;
+            update_target.seek(SeekFrom::Start(byte_offset))?;
+            // the buffered block is kept in sync with what is written.
+            let mut cached_unit = match update_block_buffer {
+                BlockBufferState::AfterReadBlock {
+                    bytes_block_buffer,
+                    bytes_position,
+                    ..
+                } => Some(&mut bytes_block_buffer[*bytes_position..*bytes_position + size_of_t]),
+                _ => None,
+            };
+            let mut written_len = 0;
+            let mut result = Ok(());
             F::convert_unit_to_blob(caret_value, &mut |data| {
-                write_target.write_all(data).expect("fail to write data");
-            });
+                if result.is_err() {
+                    return;
+                }
+                result = update_target.write_all(data);
+                if let (Ok(()), Some(cached_unit)) = (&result, &mut cached_unit) {
+                    cached_unit[written_len..written_len + data.len()].copy_from_slice(data);
+                }
+                written_len += data.len();
+            })?;
+            result?;
+            assert!(written_len == size_of_t);
+            match update_block_buffer {
+                BlockBufferState::AfterReadBlock {
+                    bytes_avail_length,
+                    bytes_position,
+                    bytes_buffer,
+                    ..
+                } => {
+                    // leave the target right after the buffered block.
+                    *bytes_buffer = None;
+                    let block_end =
+                        byte_offset - *bytes_position as u64 + *bytes_avail_length as u64;
+                    update_target.seek(SeekFrom::Start(block_end))?;
+                    update_block_buffer.get(update_target, update_position)
+                }
+                BlockBufferState::UnknownState { .. } | BlockBufferState::Eof { .. } => {
+                    *update_position += 1;
+                    Ok(())
+                }
+            }
         }
-        _ => {
-            panic!("file not in generation mode!");
+        _ => Err(file_state.wrong_mode_error("generation")),
+    }
+}
+
+pub fn put<F: PascalFile>(file: &mut F) {
+    let result = try_put(file);
+    unwrap_or_record(file, result, ())
+}
+
+// Like Turbo Pascal's `BlockRead`: fills `units` starting with the current
+// unit, as if by that many `buffer_variable` and `get` pairs, and returns the
+// count read, which is short only at the end of the file. An incomplete unit
+// there is reported as `TruncatedUnit` by the following call.
+pub fn try_read_units<F: PascalFile>(
+    file: &mut F,
+    units: &mut [F::Unit],
+) -> Result<usize, PascalIoError> {
+    let file_state = file.file_state_mut();
+    match file_state {
+        FileState::BlockInspectionMode {
+            read_block_buffer,
+            read_target,
+            read_position,
+            ..
+        } => read_block_buffer.read_units::<F, _>(read_target, read_position, units),
+        FileState::UpdateMode {
+            update_block_buffer,
+            update_target,
+            update_position,
+            write_buffer,
+            ..
+        } => {
+            *write_buffer = None;
+            update_block_buffer.read_units::<F, _>(update_target, update_position, units)
         }
+        _ => Err(file_state.wrong_mode_error("block inspection")),
     }
 }
 
-pub fn get<F: PascalFile>(file: &mut F) {
-    match file.file_state_mut() {
-        FileState::LineInspectionMode {
-            read_line_buffer, ..
-        } => match read_line_buffer {
-            LineBufferState::Eof => {
-                panic!("file eof reached");
-            }
-            LineBufferState::UnknownState { .. } => {
-                file.file_state_mut().refill::<F>();
-            }
-            LineBufferState::AfterReadLine {
-                line_buffer,
-                line_position,
-                line_no_more,
-            } => {
-                if *line_position + 1 < line_buffer.len() {
-                    *line_position += 1;
-                } else if *line_no_more {
-                    *read_line_buffer = LineBufferState::Eof
-                } else {
-                    *read_line_buffer = LineBufferState::UnknownState {
-                        initial_line: false,
-                    };
+pub fn read_units<F: PascalFile>(file: &mut F, units: &mut [F::Unit]) -> usize {
+    let result = try_read_units(file, units);
+    unwrap_or_record(file, result, 0)
+}
+
+// Like Turbo Pascal's `BlockWrite`: writes `units` as if by that many
+// `buffer_variable_assign` and `put` pairs.
+pub fn try_write_units<F: PascalFile>(file: &mut F, units: &[F::Unit]) -> Result<(), PascalIoError>
+where
+    F::Unit: Clone,
+{
+    if let FileState::UpdateMode {
+        update_block_buffer,
+        update_target,
+        update_position,
+        write_buffer,
+        ..
+    } = file.file_state_mut()
+    {
+        if units.is_empty() {
+            return Ok(());
+        }
+        let mut bytes = vec![];
+        for unit in units {
+            F::convert_unit_to_blob(unit.clone(), &mut |data| bytes.extend_from_slice(data))?;
+        }
+        assert!(bytes.len() == core::mem::size_of_val(units));
+        *write_buffer = None;
+        return update_block_buffer.write_in_place(update_target, update_position, &bytes);
+    }
+    let mut write_target = file
+        .file_state_mut()
+        .discard_buffer_variable_value_and_get_write_target()?;
+    for unit in units {
+        write_target.write_unit::<F>(unit.clone())?;
+    }
+    Ok(())
+}
+
+pub fn write_units<F: PascalFile>(file: &mut F, units: &[F::Unit])
+where
+    F::Unit: Clone,
+{
+    let result = try_write_units(file, units);
+    unwrap_or_record(file, result, ())
+}
+
+pub fn try_get<F: PascalFile>(file: &mut F) -> Result<(), PascalIoError> {
+    loop {
+        let file_state = file.file_state_mut();
+        match file_state {
+            FileState::LineInspectionMode {
+                read_line_buffer, ..
+            } => match read_line_buffer {
+                LineBufferState::Eof => return Err(PascalIoError::Eof),
+                // `UnknownState` means `f^` is the next unit, not yet loaded, so
+                // load it and then move past it. Only loading it would make this
+                // `get` a no-op, losing one unit after `reset` and at the start
+                // of every line that was not inspected before the `get`.
+                LineBufferState::UnknownState { .. } => {
+                    file.file_state_mut().refill::<F>()?;
+                    continue;
                 }
-            }
-        },
-        FileState::BlockInspectionMode {
-            read_block_buffer, ..
-        } => match read_block_buffer {
-            BlockBufferState::Eof => {
-                panic!("file eof reached");
-            }
-            BlockBufferState::UnknownState => {
-                file.file_state_mut().refill::<F>();
-            }
-            BlockBufferState::AfterReadBlock {
-                bytes_avail_length,
-                bytes_position,
-                bytes_buffer,
+                LineBufferState::AfterReadLine {
+                    line_buffer,
+                    line_position,
+                    line_no_more,
+                } => {
+                    if *line_position + 1 < line_buffer.len() {
+                        *line_position += 1;
+                    } else if *line_no_more {
+                        *read_line_buffer = LineBufferState::Eof
+                    } else {
+                        *read_line_buffer = LineBufferState::UnknownState {
+                            initial_line: false,
+                            line_buffer: core::mem::take(line_buffer),
+                        };
+                    }
+                    return Ok(());
+                }
+            },
+            FileState::BlockInspectionMode {
+                read_block_buffer,
+                read_target,
+                read_position,
+                ..
+            } => return read_block_buffer.get(read_target, read_position),
+            FileState::UpdateMode {
+                update_block_buffer,
+                update_target,
+                update_position,
+                write_buffer,
                 ..
             } => {
-                let size_of_t = core::mem::size_of::<F::Unit>();
-                assert!(size_of_t > 0);
-                let bytes_position_end = *bytes_position + size_of_t;
-                let new_bytes_position_end = bytes_position_end + size_of_t;
-                if new_bytes_position_end > *bytes_avail_length {
-                    file.file_state_mut().refill::<F>();
-                    return;
-                }
-                *bytes_buffer = None;
-                *bytes_position = bytes_position_end;
+                *write_buffer = None;
+                return update_block_buffer.get(update_target, update_position);
             }
-        },
-        _ => {
-            panic!("file not in inspection mode");
+            _ => return Err(file_state.wrong_mode_error("inspection")),
         }
     }
 }
 
-pub fn buffer_variable<F: PascalFile>(file: &mut F) -> F::Unit
+pub fn get<F: PascalFile>(file: &mut F) {
+    let result = try_get(file);
+    unwrap_or_record(file, result, ())
+}
+
+pub fn try_buffer_variable<F: PascalFile>(file: &mut F) -> Result<F::Unit, PascalIoError>
 where
     F::Unit: Clone,
 {
     loop {
-        match file.file_state_mut() {
+        let file_state = file.file_state_mut();
+        match file_state {
             FileState::LineInspectionMode {
                 read_line_buffer, ..
             } => match read_line_buffer {
                 LineBufferState::Eof => {
-                    panic!("file eof reached");
+                    return Err(PascalIoError::Eof);
                 }
                 LineBufferState::UnknownState { .. } => {
-                    file.file_state_mut().refill::<F>();
+                    file.file_state_mut().refill::<F>()?;
                     continue;
                 }
                 LineBufferState::AfterReadLine {
@@ -398,95 +1623,88 @@ where
                     line_position,
                     ..
                 } => {
-                    return line_buffer[*line_position].clone();
+                    return Ok(line_buffer[*line_position].clone());
                 }
             },
             FileState::BlockInspectionMode {
-                read_block_buffer, ..
-            } => match read_block_buffer {
-                BlockBufferState::Eof => {
-                    panic!("file eof reached");
-                }
-                BlockBufferState::UnknownState => {
-                    file.file_state_mut().refill::<F>();
-                    continue;
-                }
-                BlockBufferState::AfterReadBlock {
-                    bytes_block_buffer,
-                    bytes_buffer,
-                    bytes_position,
-                    ..
-                } => match bytes_buffer {
-                    None => {
-                        let size_of_t = core::mem::size_of::<F::Unit>();
-                        assert!(size_of_t > 0);
-                        let bytes_position_end = *bytes_position + size_of_t;
-                        let v = F::convert_blob_to_unit(
-                            &bytes_block_buffer[*bytes_position..bytes_position_end],
-                        );
-                        *bytes_buffer = Some(v.clone());
-                        return v;
-                    }
-                    Some(v) => {
-                        return v.clone();
-                    }
-                },
-            },
-            _ => panic!("file not in inspection mode"),
+                read_block_buffer,
+                read_target,
+                ..
+            } => return read_block_buffer.buffer_variable::<F, _>(read_target),
+            FileState::UpdateMode {
+                write_buffer: Some(v),
+                ..
+            } => return Ok(v.clone()),
+            FileState::UpdateMode {
+                update_block_buffer,
+                update_target,
+                ..
+            } => return update_block_buffer.buffer_variable::<F, _>(update_target),
+            _ => return Err(file_state.wrong_mode_error("inspection")),
         }
     }
 }
 
-pub fn eof<F: PascalFile>(file: &mut F) -> bool {
+pub fn buffer_variable<F: PascalFile>(file: &mut F) -> F::Unit
+where
+    F::Unit: Clone,
+{
+    let result = try_buffer_variable(file);
+    unwrap_or_record(file, result, F::eoln_unit())
+}
+
+pub fn try_eof<F: PascalFile>(file: &mut F) -> Result<bool, PascalIoError> {
     loop {
-        match file.file_state() {
+        match file.file_state_mut() {
             FileState::LineInspectionMode {
                 read_line_buffer, ..
             } => match read_line_buffer {
                 LineBufferState::Eof => {
-                    return true;
+                    return Ok(true);
                 }
                 LineBufferState::UnknownState { .. } => {
-                    file.file_state_mut().refill::<F>();
+                    file.file_state_mut().refill::<F>()?;
                     continue;
                 }
                 LineBufferState::AfterReadLine { .. } => {
-                    return false;
-                }
-            },
-            FileState::BlockInspectionMode {
-                read_block_buffer, ..
-            } => match read_block_buffer {
-                BlockBufferState::Eof => {
-                    return true;
-                }
-                BlockBufferState::UnknownState { .. } => {
-                    file.file_state_mut().refill::<F>();
-                    continue;
-                }
-                BlockBufferState::AfterReadBlock { .. } => {
-                    return false;
+                    return Ok(false);
                 }
             },
+            FileState::BlockInspectionMode {
+                read_block_buffer,
+                read_target,
+                ..
+            } => return read_block_buffer.eof(read_target),
+            FileState::UpdateMode {
+                update_block_buffer,
+                update_target,
+                ..
+            } => return update_block_buffer.eof(update_target),
             FileState::GenerationMode { .. } => {
-                return true;
+                return Ok(true);
             }
-            _ => panic!("file not in any mode"),
+            FileState::Undefined | FileState::Failed => return Err(PascalIoError::NotOpen),
         }
     }
 }
 
-pub fn eoln<F: PascalFile>(file: &mut F) -> bool {
+pub fn eof<F: PascalFile>(file: &mut F) -> bool {
+    let result = try_eof(file);
+    unwrap_or_record(file, result, true)
+}
+
+pub fn try_eoln<F: PascalFile>(file: &mut F) -> Result<bool, PascalIoError> {
     loop {
-        match file.file_state() {
+        let file_state = file.file_state();
+        match file_state {
             FileState::LineInspectionMode {
                 read_line_buffer, ..
             } => match read_line_buffer {
                 LineBufferState::Eof => {
-                    panic!("file eof reached");
+                    return Err(PascalIoError::Eof);
                 }
                 LineBufferState::UnknownState { .. } => {
-                    file.file_state_mut().refill::<F>();
+                    file.file_state_mut().refill::<F>()?;
                     continue;
                 }
                 LineBufferState::AfterReadLine {
@@ -494,69 +1712,509 @@ pub fn eoln<F: PascalFile>(file: &mut F) -> bool {
                     line_position,
                     ..
                 } => {
-                    return F::is_eoln_unit(&line_buffer[*line_position]);
+                    return Ok(F::is_eoln_unit(&line_buffer[*line_position]));
                 }
             },
-            FileState::BlockInspectionMode { .. } => panic!("file is not text file"),
-            _ => panic!("file not in inspection mode"),
+            _ => return Err(file_state.wrong_mode_error("line inspection")),
+        }
+    }
+}
+
+pub fn eoln<F: PascalFile>(file: &mut F) -> bool {
+    let result = try_eoln(file);
+    unwrap_or_record(file, result, true)
+}
+
+pub fn try_line_truncated<F: PascalFile>(file: &mut F) -> Result<bool, PascalIoError> {
+    loop {
+        let file_state = file.file_state();
+        match file_state {
+            FileState::LineInspectionMode {
+                read_line_buffer: LineBufferState::UnknownState { .. },
+                ..
+            } => {
+                file.file_state_mut().refill::<F>()?;
+                continue;
+            }
+            FileState::LineInspectionMode {
+                read_flag_line_truncated,
+                ..
+            } => return Ok(*read_flag_line_truncated),
+            _ => return Err(file_state.wrong_mode_error("line inspection")),
         }
     }
 }
 
+// Whether the current line was cut at the maximum line length.
+pub fn line_truncated<F: PascalFile>(file: &mut F) -> bool {
+    let result = try_line_truncated(file);
+    unwrap_or_record(file, result, false)
+}
+
+pub fn try_write<F: PascalFile, T: fmt::Display>(
+    file: &mut F,
+    val: T,
+) -> Result<(), PascalIoError> {
+    let mut write_target = file
+        .file_state_mut()
+        .discard_buffer_variable_value_and_get_write_target()?;
+    write_target.write_text::<F>(format_args!("{}", val))?;
+    Ok(())
+}
+
 pub fn write<F: PascalFile, T: fmt::Display>(file: &mut F, val: T) {
-    let write_target = file
+    let result = try_write(file, val);
+    unwrap_or_record(file, result, ())
+}
+
+pub fn try_write_formatted<F: PascalFile, T: PascalWrite + ?Sized>(
+    file: &mut F,
+    val: &T,
+    width: Option<usize>,
+    precision: Option<usize>,
+) -> Result<(), PascalIoError> {
+    try_write(file, PascalFormatted::new(val, width, precision))
+}
+
+pub fn write_formatted<F: PascalFile, T: PascalWrite + ?Sized>(
+    file: &mut F,
+    val: &T,
+    width: Option<usize>,
+    precision: Option<usize>,
+) {
+    let result = try_write_formatted(file, val, width, precision);
+    unwrap_or_record(file, result, ())
+}
+
+pub fn try_write_ln<F: PascalFile, T: fmt::Display>(
+    file: &mut F,
+    val: T,
+) -> Result<(), PascalIoError> {
+    let mut write_target = file
         .file_state_mut()
-        .discard_buffer_variable_value_and_get_write_target();
-    write!(write_target, "{}", val).unwrap();
+        .discard_buffer_variable_value_and_get_write_target()?;
+    write_target.write_text::<F>(format_args!("{}", val))?;
+    write_target.write_line_terminator()?;
+    Ok(())
 }
 
 pub fn write_ln<F: PascalFile, T: fmt::Display>(file: &mut F, val: T) {
-    let write_target = file
+    let result = try_write_ln(file, val);
+    unwrap_or_record(file, result, ())
+}
+
+pub fn try_write_ln_noargs<F: PascalFile>(file: &mut F) -> Result<(), PascalIoError> {
+    let mut write_target = file
         .file_state_mut()
-        .discard_buffer_variable_value_and_get_write_target();
-    writeln!(write_target, "{}", val).unwrap();
+        .discard_buffer_variable_value_and_get_write_target()?;
+    write_target.write_line_terminator()?;
+    Ok(())
 }
 
 pub fn write_ln_noargs<F: PascalFile>(file: &mut F) {
-    let write_target = file
-        .file_state_mut()
-        .discard_buffer_variable_value_and_get_write_target();
-    writeln!(write_target).unwrap();
+    let result = try_write_ln_noargs(file);
+    unwrap_or_record(file, result, ())
 }
 
-pub fn write_binary<F: PascalFile, T: ToBlob>(file: &mut F, val: T) {
+pub fn try_write_binary<F: PascalFile, T: ToBlob>(
+    file: &mut F,
+    val: T,
+) -> Result<(), PascalIoError> {
     use core::borrow::Borrow;
-    let write_target = file
+    let mut write_target = file
         .file_state_mut()
-        .discard_buffer_variable_value_and_get_write_target();
+        .discard_buffer_variable_value_and_get_write_target()?;
     let blob = val.to_blob();
-    write_target.write_all(blob.borrow()).unwrap();
+    write_target.write_all(blob.borrow())?;
+    Ok(())
 }
 
-pub fn r#break<F: PascalFile>(file: &mut F) {
-    let write_target = file
+pub fn write_binary<F: PascalFile, T: ToBlob>(file: &mut F, val: T) {
+    let result = try_write_binary(file, val);
+    unwrap_or_record(file, result, ())
+}
+
+// Reads a `T` from the next `size_of::<T>()` bytes, starting with the current
+// unit, whatever the unit type of the file. The size must be a multiple of
+// the unit size. It includes any padding, so records with gaps between their
+// fields are better read field by field.
+pub fn try_read_binary<F: PascalFile, T: FromBlob>(file: &mut F) -> Result<T, PascalIoError> {
+    let size_of_v = core::mem::size_of::<T>();
+    if !size_of_v.is_multiple_of(core::mem::size_of::<F::Unit>()) {
+        return Err(io::Error::from(io::ErrorKind::InvalidInput).into());
+    }
+    let mut blob = vec![0u8; size_of_v];
+    let file_state = file.file_state_mut();
+    let read_len = match file_state {
+        FileState::BlockInspectionMode {
+            read_block_buffer,
+            read_target,
+            read_position,
+            ..
+        } => read_block_buffer.read_bytes(read_target, read_position, &mut blob)?,
+        FileState::UpdateMode {
+            update_block_buffer,
+            update_target,
+            update_position,
+            write_buffer,
+            ..
+        } => {
+            *write_buffer = None;
+            update_block_buffer.read_bytes(update_target, update_position, &mut blob)?
+        }
+        _ => return Err(file_state.wrong_mode_error("block inspection")),
+    };
+    if read_len < size_of_v {
+        return Err(PascalIoError::Eof);
+    }
+    Ok(T::from_blob(&blob))
+}
+
+pub fn read_binary<F: PascalFile, T: FromBlob + Default>(file: &mut F) -> T {
+    let result = try_read_binary(file);
+    unwrap_or_record(file, result, T::default())
+}
+
+pub fn try_break<F: PascalFile>(file: &mut F) -> Result<(), PascalIoError> {
+    if let FileState::UpdateMode { update_target, .. } = file.file_state_mut() {
+        update_target.flush()?;
+        return Ok(());
+    }
+    let mut write_target = file
         .file_state_mut()
-        .discard_buffer_variable_value_and_get_write_target();
-    write_target.flush().unwrap();
+        .discard_buffer_variable_value_and_get_write_target()?;
+    write_target.flush()?;
+    Ok(())
+}
+
+pub fn r#break<F: PascalFile>(file: &mut F) {
+    let result = try_break(file);
+    unwrap_or_record(file, result, ())
+}
+
+pub fn try_position<F: PascalFile>(file: &mut F) -> Result<u64, PascalIoError> {
+    let file_state = file.file_state();
+    match file_state {
+        FileState::BlockInspectionMode { read_position, .. } => Ok(*read_position),
+        FileState::UpdateMode {
+            update_position, ..
+        } => Ok(*update_position),
+        _ => Err(file_state.wrong_mode_error("block inspection")),
+    }
+}
+
+pub fn position<F: PascalFile>(file: &mut F) -> u64 {
+    let result = try_position(file);
+    unwrap_or_record(file, result, 0)
+}
+
+pub fn try_seek<F: PascalFile>(file: &mut F, unit_index: u64) -> Result<(), PascalIoError> {
+    let file_state = file.file_state_mut();
+    match file_state {
+        FileState::BlockInspectionMode {
+            read_block_buffer,
+            read_target: BlockReadTarget::Seekable(read_target),
+            read_position,
+            ..
+        } => read_block_buffer.seek(read_target, read_position, unit_index),
+        FileState::UpdateMode {
+            update_block_buffer,
+            update_target,
+            update_position,
+            write_buffer,
+            ..
+        } => {
+            *write_buffer = None;
+            update_block_buffer.seek(update_target, update_position, unit_index)
+        }
+        FileState::BlockInspectionMode { .. } => Err(PascalIoError::NotSeekable),
+        _ => Err(file_state.wrong_mode_error("block inspection")),
+    }
+}
+
+pub fn seek<F: PascalFile>(file: &mut F, unit_index: u64) {
+    let result = try_seek(file, unit_index);
+    unwrap_or_record(file, result, ())
+}
+
+pub fn try_write_position<F: PascalFile>(file: &mut F) -> Result<u64, PascalIoError> {
+    let file_state = file.file_state();
+    match file_state {
+        FileState::GenerationMode { write_position, .. } => Ok(*write_position),
+        _ => Err(file_state.wrong_mode_error("generation")),
+    }
+}
+
+// Byte offset of the next byte to be written.
+pub fn write_position<F: PascalFile>(file: &mut F) -> u64 {
+    let result = try_write_position(file);
+    unwrap_or_record(file, result, 0)
+}
+
+pub fn try_patch_binary<F: PascalFile, T: ToBlob>(
+    file: &mut F,
+    byte_offset: u64,
+    val: T,
+) -> Result<(), PascalIoError> {
+    use core::borrow::Borrow;
+    let file_state = file.file_state_mut();
+    match file_state {
+        FileState::GenerationMode {
+            write_target: WriteTarget::Seekable(write_target),
+            write_bytes_buffer,
+            write_position,
+            ..
+        } => {
+            let blob = val.to_blob();
+            let blob: &[u8] = blob.borrow();
+            match byte_offset.checked_add(blob.len() as u64) {
+                Some(end) if end <= *write_position => {}
+                _ => return Err(io::Error::from(io::ErrorKind::InvalidInput).into()),
+            }
+            flush_write_bytes_buffer(write_target, write_bytes_buffer)?;
+            write_target.seek(SeekFrom::Start(byte_offset))?;
+            write_target.write_all(blob)?;
+            write_target.seek(SeekFrom::Start(*write_position))?;
+            Ok(())
+        }
+        FileState::GenerationMode { .. } => Err(PascalIoError::NotSeekable),
+        _ => Err(file_state.wrong_mode_error("generation")),
+    }
+}
+
+// Overwrites already written bytes at `byte_offset`, leaving the write position unchanged.
+pub fn patch_binary<F: PascalFile, T: ToBlob>(file: &mut F, byte_offset: u64, val: T) {
+    let result = try_patch_binary(file, byte_offset, val);
+    unwrap_or_record(file, result, ())
+}
+
+pub fn try_read_onearg<F: PascalFile>(file: &mut F) -> Result<F::Unit, PascalIoError>
+where
+    F::Unit: Copy,
+{
+    let v = try_buffer_variable(file)?;
+    try_get(file)?;
+    Ok(v)
 }
 
 pub fn read_onearg<F: PascalFile>(file: &mut F) -> F::Unit
 where
     F::Unit: Copy,
 {
-    let v = buffer_variable(file);
-    get(file);
-    v
+    let result = try_read_onearg(file);
+    unwrap_or_record(file, result, F::eoln_unit())
+}
+
+fn try_skip_blanks<F: PascalFile>(file: &mut F) -> Result<(), PascalIoError>
+where
+    F::Unit: TextUnit,
+{
+    loop {
+        if try_eof(file)? {
+            return Err(PascalIoError::Eof);
+        }
+        let unit = try_buffer_variable(file)?;
+        if !F::is_eoln_unit(&unit) && !matches!(unit.to_char(), ' ' | '\t') {
+            return Ok(());
+        }
+        try_get(file)?;
+    }
+}
+
+fn try_peek_char<F: PascalFile>(file: &mut F) -> Result<Option<char>, PascalIoError>
+where
+    F::Unit: TextUnit,
+{
+    if try_eof(file)? {
+        return Ok(None);
+    }
+    let unit = try_buffer_variable(file)?;
+    if F::is_eoln_unit(&unit) {
+        Ok(None)
+    } else {
+        Ok(Some(unit.to_char()))
+    }
+}
+
+fn try_scan_sign<F: PascalFile>(file: &mut F, text: &mut String) -> Result<(), PascalIoError>
+where
+    F::Unit: TextUnit,
+{
+    if let Some(c @ ('+' | '-')) = try_peek_char(file)? {
+        text.push(c);
+        try_get(file)?;
+    }
+    Ok(())
+}
+
+fn try_scan_digits<F: PascalFile>(file: &mut F, text: &mut String) -> Result<(), PascalIoError>
+where
+    F::Unit: TextUnit,
+{
+    let mut digit_count = 0;
+    while let Some(c) = try_peek_char(file)? {
+        if !c.is_ascii_digit() {
+            break;
+        }
+        text.push(c);
+        try_get(file)?;
+        digit_count += 1;
+    }
+    if digit_count == 0 {
+        return Err(PascalIoError::InvalidNumber);
+    }
+    Ok(())
+}
+
+pub fn try_read_integer<F: PascalFile, I: TryFrom<i128>>(file: &mut F) -> Result<I, PascalIoError>
+where
+    F::Unit: TextUnit,
+{
+    try_skip_blanks(file)?;
+    let mut text = String::new();
+    try_scan_sign(file, &mut text)?;
+    try_scan_digits(file, &mut text)?;
+    let value: i128 = text.parse().map_err(|_| PascalIoError::NumberOverflow)?;
+    I::try_from(value).map_err(|_| PascalIoError::NumberOverflow)
+}
+
+pub fn read_integer<F: PascalFile, I: TryFrom<i128> + Default>(file: &mut F) -> I
+where
+    F::Unit: TextUnit,
+{
+    let result = try_read_integer(file);
+    unwrap_or_record(file, result, I::default())
+}
+
+pub fn try_read_real<F: PascalFile>(file: &mut F) -> Result<f64, PascalIoError>
+where
+    F::Unit: TextUnit,
+{
+    try_skip_blanks(file)?;
+    let mut text = String::new();
+    try_scan_sign(file, &mut text)?;
+    try_scan_digits(file, &mut text)?;
+    if try_peek_char(file)? == Some('.') {
+        text.push('.');
+        try_get(file)?;
+        try_scan_digits(file, &mut text)?;
+    }
+    if let Some(c @ ('e' | 'E')) = try_peek_char(file)? {
+        text.push(c);
+        try_get(file)?;
+        try_scan_sign(file, &mut text)?;
+        try_scan_digits(file, &mut text)?;
+    }
+    let value: f64 = text.parse().map_err(|_| PascalIoError::InvalidNumber)?;
+    if value.is_infinite() {
+        return Err(PascalIoError::NumberOverflow);
+    }
+    Ok(value)
+}
+
+pub fn read_real<F: PascalFile>(file: &mut F) -> f64
+where
+    F::Unit: TextUnit,
+{
+    let result = try_read_real(file);
+    unwrap_or_record(file, result, 0.0)
+}
+
+pub fn try_read_ln<F: PascalFile>(file: &mut F) -> Result<(), PascalIoError> {
+    while !try_eoln(file)? {
+        try_get(file)?;
+    }
+    try_get(file)
 }
 
 pub fn read_ln<F: PascalFile>(file: &mut F) {
-    while !eoln(file) {
-        get(file);
+    let result = try_read_ln(file);
+    unwrap_or_record(file, result, ())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrailingBlanks {
+    Keep,
+    StripSpaces,
+    StripSpacesAndTabs,
+}
+
+impl TrailingBlanks {
+    fn is_stripped(self, c: char) -> bool {
+        match self {
+            TrailingBlanks::Keep => false,
+            TrailingBlanks::StripSpaces => c == ' ',
+            TrailingBlanks::StripSpacesAndTabs => c == ' ' || c == '\t',
+        }
+    }
+}
+
+// Moves the rest of the current line into `buffer`, leaving the file at its
+// end of line, and returns the length after removing trailing blanks.
+// `bypass_eoln` skips the end of line a previous call stopped at. Returns
+// `None` at end of file. A line longer than `buffer` fills it and fails with
+// `LineTooLong`, leaving the file at the first unit that did not fit.
+pub fn try_input_ln<F: PascalFile>(
+    file: &mut F,
+    bypass_eoln: bool,
+    buffer: &mut [F::Unit],
+    trailing_blanks: TrailingBlanks,
+) -> Result<Option<usize>, PascalIoError>
+where
+    F::Unit: TextUnit,
+{
+    let file_state = file.file_state();
+    if !matches!(file_state, FileState::LineInspectionMode { .. }) {
+        return Err(file_state.wrong_mode_error("line inspection"));
+    }
+    if bypass_eoln && !try_eof(file)? {
+        try_get(file)?;
+    }
+    if try_eof(file)? {
+        return Ok(None);
+    }
+    match file.file_state_mut() {
+        FileState::LineInspectionMode {
+            read_line_buffer:
+                LineBufferState::AfterReadLine {
+                    line_buffer,
+                    line_position,
+                    ..
+                },
+            ..
+        } => {
+            let line = &line_buffer[*line_position..];
+            let line_len = line.iter().position(F::is_eoln_unit).unwrap_or(line.len());
+            let copy_len = line_len.min(buffer.len());
+            buffer[..copy_len].copy_from_slice(&line[..copy_len]);
+            *line_position += copy_len;
+            if copy_len < line_len {
+                return Err(PascalIoError::LineTooLong);
+            }
+            let mut len = copy_len;
+            while len > 0 && trailing_blanks.is_stripped(buffer[len - 1].to_char()) {
+                len -= 1;
+            }
+            Ok(Some(len))
+        }
+        _ => unreachable!(),
     }
-    get(file);
 }
 
-pub fn break_in<F: PascalFile>(file: &mut F, _: bool) {
+pub fn input_ln<F: PascalFile>(
+    file: &mut F,
+    bypass_eoln: bool,
+    buffer: &mut [F::Unit],
+    trailing_blanks: TrailingBlanks,
+) -> Option<usize>
+where
+    F::Unit: TextUnit,
+{
+    let result = try_input_ln(file, bypass_eoln, buffer, trailing_blanks);
+    unwrap_or_record(file, result, None)
+}
+
+pub fn try_break_in<F: PascalFile>(file: &mut F, _: bool) -> Result<(), PascalIoError> {
     // FIXME: this seems nonstandard. Verify if this handling is correct.
     // and not sure what the 2nd argument should do here.
     let file_state = file.file_state_mut();
@@ -565,34 +2223,141 @@ pub fn break_in<F: PascalFile>(file: &mut F, _: bool) {
             read_line_buffer,
             read_flag_extra_eoln_line,
             ..
-        } => match read_line_buffer {
-            LineBufferState::AfterReadLine { line_no_more, .. } => {
-                if *line_no_more {
-                    *read_line_buffer = LineBufferState::Eof
-                } else if *read_flag_extra_eoln_line {
-                    *read_line_buffer = LineBufferState::AfterReadLine {
-                        line_buffer: vec![F::eoln_unit()],
-                        line_position: 0,
-                        line_no_more: false,
-                    };
-                } else {
-                    *read_line_buffer = LineBufferState::UnknownState {
-                        initial_line: false,
-                    };
+        } => {
+            match read_line_buffer {
+                LineBufferState::AfterReadLine {
+                    line_buffer,
+                    line_position,
+                    line_no_more,
+                } => {
+                    if *line_no_more {
+                        *read_line_buffer = LineBufferState::Eof
+                    } else if *read_flag_extra_eoln_line {
+                        line_buffer.clear();
+                        line_buffer.push(F::eoln_unit());
+                        *line_position = 0;
+                    } else {
+                        *read_line_buffer = LineBufferState::UnknownState {
+                            initial_line: false,
+                            line_buffer: core::mem::take(line_buffer),
+                        };
+                    }
                 }
+                LineBufferState::Eof | LineBufferState::UnknownState { .. } => {}
             }
-            LineBufferState::Eof | LineBufferState::UnknownState { .. } => {}
-        },
-        _ => panic!("file is not in line-inspection mode"),
+            Ok(())
+        }
+        _ => Err(file_state.wrong_mode_error("line inspection")),
     }
 }
 
+pub fn break_in<F: PascalFile>(file: &mut F, bypass: bool) {
+    let result = try_break_in(file, bypass);
+    unwrap_or_record(file, result, ())
+}
+
 pub fn erstat<F: PascalFile>(file: &mut F) -> usize {
-    file.error_state()
+    file.error_state().map_or(0, PascalIoError::code)
+}
+
+// Flushes the output, and syncs it to the disk for files opened with `/S`.
+// Leaves the file open when that fails, the buffered output itself is dropped.
+pub fn try_close<F: PascalFile>(file: &mut F) -> Result<(), PascalIoError> {
+    let file_state = file.file_state_mut();
+    match file_state {
+        FileState::GenerationMode {
+            write_target,
+            write_bytes_buffer,
+            options,
+            ..
+        } => {
+            let result = flush_write_bytes_buffer(write_target, write_bytes_buffer)
+                .and_then(|()| write_target.flush());
+            write_bytes_buffer.clear();
+            result?;
+            if options.sync_on_close {
+                write_target.sync()?;
+            }
+        }
+        FileState::UpdateMode {
+            update_target,
+            options,
+            ..
+        } => {
+            update_target.flush()?;
+            if options.sync_on_close {
+                <F::Backend as FileBackend>::sync_updater(update_target)?;
+            }
+        }
+        _ => {}
+    }
+    *file_state = FileState::default();
+    Ok(())
 }
 
 pub fn close<F: PascalFile>(file: &mut F) {
-    *file.file_state_mut() = FileState::default();
+    let result = try_close(file);
+    unwrap_or_record(file, result, ())
+}
+
+impl TextUnit for u8 {
+    fn to_char(self) -> char {
+        char::from(self)
+    }
+
+    fn from_char(c: char) -> Option<Self> {
+        u8::try_from(c).ok()
+    }
+
+    // In a single-byte encoding a byte unit is the code of its character.
+    fn from_encoded_char(encoding: TextEncoding, c: char) -> Result<Self, PascalIoError> {
+        match encoding {
+            TextEncoding::Utf8 => Ok(u8::try_from(c).unwrap_or(b'?')),
+            _ => encoding.encode_char(c),
+        }
+    }
+
+    fn to_encoded_char(self, encoding: TextEncoding) -> Result<char, PascalIoError> {
+        match encoding {
+            TextEncoding::Utf8 => Ok(char::from(self)),
+            _ => encoding.decode_byte(self),
+        }
+    }
+
+    fn eoln_unit() -> Self {
+        b'\n'
+    }
+
+    fn convert_bytes_to_units(input: &[u8], units: &mut Vec<Self>) {
+        units.extend_from_slice(input);
+    }
+
+    fn convert_unit_to_bytes(self, f: &mut dyn for<'a> FnMut(&'a [u8])) {
+        f(&[self]);
+    }
+}
+
+impl TextUnit for char {
+    fn to_char(self) -> char {
+        self
+    }
+
+    fn from_char(c: char) -> Option<Self> {
+        Some(c)
+    }
+
+    fn eoln_unit() -> Self {
+        '\n'
+    }
+
+    fn convert_bytes_to_units(input: &[u8], units: &mut Vec<Self>) {
+        units.extend(String::from_utf8_lossy(input).chars());
+    }
+
+    fn convert_unit_to_bytes(self, f: &mut dyn for<'a> FnMut(&'a [u8])) {
+        let mut buf = [0u8; 4];
+        f(self.encode_utf8(&mut buf).as_bytes());
+    }
 }
 
 impl FromBlob for u8 {
@@ -609,3 +2374,30 @@ impl ToBlob for u8 {
         [*self]
     }
 }
+
+// Wider integers are stored big-endian, the byte order of TeX's DVI and TFM
+// files. `usize` and `isize` are left out since their size varies.
+macro_rules! impl_blob_for_integer {
+    ($($t:ty),*) => {
+        $(
+            impl FromBlob for $t {
+                fn from_blob(data: &[u8]) -> Self {
+                    let mut bytes = [0; core::mem::size_of::<$t>()];
+                    assert!(data.len() == bytes.len());
+                    bytes.copy_from_slice(data);
+                    <$t>::from_be_bytes(bytes)
+                }
+            }
+
+            impl ToBlob for $t {
+                type BlobType = [u8; core::mem::size_of::<$t>()];
+
+                fn to_blob(&self) -> Self::BlobType {
+                    self.to_be_bytes()
+                }
+            }
+        )*
+    };
+}
+
+impl_blob_for_integer!(i8, i16, u16, i32, u32, i64, u64, i128, u128);
@@ -0,0 +1,100 @@
+#[macro_export]
+macro_rules! pascal_write {
+    ($file:expr, $($args:tt)+) => {{
+        let file = &mut *$file;
+        $crate::__pascal_write_args!(@value file [] $($args)+);
+    }};
+}
+
+#[macro_export]
+macro_rules! pascal_writeln {
+    ($file:expr $(,)?) => {{
+        $crate::write_ln_noargs($file);
+    }};
+    ($file:expr, $($args:tt)+) => {{
+        let file = &mut *$file;
+        $crate::__pascal_write_args!(@value file [] $($args)+);
+        $crate::write_ln_noargs(file);
+    }};
+}
+
+#[macro_export]
+macro_rules! pascal_read {
+    ($file:expr, $($arg:expr),+ $(,)?) => {{
+        let file = &mut *$file;
+        $(
+            $arg = $crate::read_onearg(&mut *file);
+        )+
+    }};
+}
+
+#[macro_export]
+macro_rules! pascal_readln {
+    ($file:expr $(,)?) => {{
+        $crate::read_ln($file);
+    }};
+    ($file:expr, $($arg:expr),+ $(,)?) => {{
+        let file = &mut *$file;
+        $(
+            $arg = $crate::read_onearg(&mut *file);
+        )+
+        $crate::read_ln(file);
+    }};
+}
+
+// Splits `value[:width[:precision]], ...` at the top-level `:` and `,` tokens.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __pascal_write_args {
+    (@value $file:ident []) => {};
+    (@value $file:ident [$($value:tt)+]) => {
+        $crate::write_formatted(&mut *$file, &($($value)+), None, None);
+    };
+    (@value $file:ident [$($value:tt)+] , $($rest:tt)*) => {
+        $crate::write_formatted(&mut *$file, &($($value)+), None, None);
+        $crate::__pascal_write_args!(@value $file [] $($rest)*);
+    };
+    (@value $file:ident [$($value:tt)+] : $($rest:tt)+) => {
+        $crate::__pascal_write_args!(@width $file [$($value)+] [] $($rest)+);
+    };
+    (@value $file:ident [$($value:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::__pascal_write_args!(@value $file [$($value)* $next] $($rest)*);
+    };
+
+    (@width $file:ident [$($value:tt)+] [$($width:tt)+]) => {
+        $crate::write_formatted(&mut *$file, &($($value)+), Some($($width)+), None);
+    };
+    (@width $file:ident [$($value:tt)+] [$($width:tt)+] , $($rest:tt)*) => {
+        $crate::write_formatted(&mut *$file, &($($value)+), Some($($width)+), None);
+        $crate::__pascal_write_args!(@value $file [] $($rest)*);
+    };
+    (@width $file:ident [$($value:tt)+] [$($width:tt)+] : $($rest:tt)+) => {
+        $crate::__pascal_write_args!(@precision $file [$($value)+] [$($width)+] [] $($rest)+);
+    };
+    (@width $file:ident [$($value:tt)+] [$($width:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::__pascal_write_args!(@width $file [$($value)+] [$($width)* $next] $($rest)*);
+    };
+
+    (@precision $file:ident [$($value:tt)+] [$($width:tt)+] [$($precision:tt)+]) => {
+        $crate::write_formatted(
+            &mut *$file,
+            &($($value)+),
+            Some($($width)+),
+            Some($($precision)+),
+        );
+    };
+    (@precision $file:ident [$($value:tt)+] [$($width:tt)+] [$($precision:tt)+] , $($rest:tt)*) => {
+        $crate::write_formatted(
+            &mut *$file,
+            &($($value)+),
+            Some($($width)+),
+            Some($($precision)+),
+        );
+        $crate::__pascal_write_args!(@value $file [] $($rest)*);
+    };
+    (@precision $file:ident [$($value:tt)+] [$($width:tt)+] [$($precision:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::__pascal_write_args!(
+            @precision $file [$($value)+] [$($width)+] [$($precision)* $next] $($rest)*
+        );
+    };
+}
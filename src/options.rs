@@ -0,0 +1,138 @@
+use crate::{PascalIoError, TextEncoding};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    // Ends input lines at any of `\n`, `\r\n` and a lone `\r`, writes `\n`.
+    #[default]
+    Auto,
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl LineEnding {
+    pub(crate) fn terminator(self) -> &'static str {
+        match self {
+            LineEnding::Auto | LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+}
+
+pub const DEFAULT_BLOCK_SIZE: usize = 512;
+pub const DEFAULT_WRITE_BUFFER_CAPACITY: usize = 8192;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineOverflow {
+    // Skip the line and fail with `LineLimitExceeded`.
+    #[default]
+    Error,
+    // Continue the line as a new one.
+    Split,
+    // Drop the rest of the line, see `line_truncated`.
+    Truncate,
+}
+
+// Settings from the options string of `reset`, `rewrite`, `extend` and `update`.
+//
+// The string is a sequence of switches, each `/NAME` or `/NAME=VALUE`, with
+// optional blanks in between. Names and values are case-insensitive:
+//
+// - `/O`: record failures in `erstat` instead of panicking, for this opening.
+// - `/I`, `/B`: treat text input as interactive or batch regardless of the
+//   terminal detection.
+// - `/L=AUTO|LF|CRLF|CR`: line ending policy of text files.
+// - `/E=UTF8|LATIN1|ASCII|CP1252|CP437`: encoding of the file contents,
+//   transcoded to and from the encoding of the file type.
+// - `/A`: make `rewrite` append to the existing file like `extend`.
+// - `/M=<n>[,ERROR|,SPLIT|,TRUNCATE]`: limit text input lines to `n` bytes,
+//   counting the line terminator, and choose what happens to longer ones.
+// - `/K=<n>`: read binary files in blocks of about `n` bytes instead of
+//   `DEFAULT_BLOCK_SIZE`.
+// - `/W=<n>`: keep up to `n` bytes of output before passing it on to the file,
+//   instead of `DEFAULT_WRITE_BUFFER_CAPACITY`. `/W=0` passes every write on.
+// - `/S`: have `close` sync the written data to the disk, which the backend
+//   of the file must support.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FileOptions {
+    pub error_state_mode: bool,
+    pub interactive: Option<bool>,
+    pub line_ending: LineEnding,
+    pub encoding: Option<TextEncoding>,
+    pub append: bool,
+    pub max_line_length: Option<usize>,
+    pub line_overflow: LineOverflow,
+    pub block_size: Option<usize>,
+    pub write_buffer_capacity: Option<usize>,
+    pub sync_on_close: bool,
+}
+
+impl FromStr for FileOptions {
+    type Err = PascalIoError;
+
+    fn from_str(options: &str) -> Result<Self, Self::Err> {
+        let mut result = FileOptions::default();
+        let mut switches = options.split('/');
+        if !switches.next().unwrap().trim().is_empty() {
+            return Err(PascalIoError::InvalidOption(options.to_owned()));
+        }
+        for switch in switches {
+            let switch = switch.trim();
+            let invalid = || PascalIoError::InvalidOption(format!("/{}", switch));
+            let (name, value) = match switch.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim())),
+                None => (switch, None),
+            };
+            let name = name.to_ascii_uppercase();
+            let value = value.map(str::to_ascii_uppercase);
+            match (name.as_str(), value.as_deref()) {
+                ("O", None) => result.error_state_mode = true,
+                ("I", None) => result.interactive = Some(true),
+                ("B", None) => result.interactive = Some(false),
+                ("A", None) => result.append = true,
+                ("S", None) => result.sync_on_close = true,
+                ("L", Some("AUTO")) => result.line_ending = LineEnding::Auto,
+                ("L", Some("LF")) => result.line_ending = LineEnding::Lf,
+                ("L", Some("CRLF")) => result.line_ending = LineEnding::CrLf,
+                ("L", Some("CR")) => result.line_ending = LineEnding::Cr,
+                ("E", Some("UTF8" | "UTF-8")) => result.encoding = Some(TextEncoding::Utf8),
+                ("E", Some("LATIN1" | "LATIN-1" | "ISO-8859-1")) => {
+                    result.encoding = Some(TextEncoding::Latin1)
+                }
+                ("E", Some("ASCII")) => result.encoding = Some(TextEncoding::Ascii),
+                ("E", Some("WINDOWS-1252" | "CP1252")) => {
+                    result.encoding = Some(TextEncoding::Windows1252)
+                }
+                ("E", Some("CP437" | "IBM437")) => result.encoding = Some(TextEncoding::Cp437),
+                ("M", Some(value)) => {
+                    let (length, overflow) = match value.split_once(',') {
+                        Some((length, overflow)) => (length.trim(), Some(overflow.trim())),
+                        None => (value, None),
+                    };
+                    match length.parse() {
+                        Ok(length) if length > 0 => result.max_line_length = Some(length),
+                        _ => return Err(invalid()),
+                    }
+                    result.line_overflow = match overflow {
+                        None | Some("ERROR") => LineOverflow::Error,
+                        Some("SPLIT") => LineOverflow::Split,
+                        Some("TRUNCATE") => LineOverflow::Truncate,
+                        Some(_) => return Err(invalid()),
+                    };
+                }
+                ("K", Some(value)) => match value.parse() {
+                    Ok(size) if size > 0 => result.block_size = Some(size),
+                    _ => return Err(invalid()),
+                },
+                ("W", Some(value)) => match value.parse() {
+                    Ok(capacity) => result.write_buffer_capacity = Some(capacity),
+                    _ => return Err(invalid()),
+                },
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(result)
+    }
+}
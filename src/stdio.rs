@@ -0,0 +1,218 @@
+use crate::{BoxedBackend, FileBackend, FileState, PascalFile, PascalIoError, TextUnit};
+use std::fmt;
+use std::io::{self, IsTerminal};
+
+pub(crate) fn unsupported_operation() -> PascalIoError {
+    PascalIoError::Io(io::ErrorKind::Unsupported.into())
+}
+
+pub(crate) fn convert_crlf_to_lf(input: &mut Vec<u8>) {
+    if input.ends_with(b"\r\n") {
+        input.truncate(input.len() - 2);
+        input.push(b'\n');
+    }
+}
+
+// `$name<B>` makes the backend a type parameter defaulting to `BoxedBackend`.
+macro_rules! impl_text_file {
+    (
+        $name:ident $(<$param:ident>)?,
+        backend($backend:ty),
+        open_read($read_path:pat) $open_read:expr,
+        open_write($write_path:pat) $open_write:expr
+        $(, open_append($append_path:pat) $open_append:expr)?
+    ) => {
+        pub struct $name<U = char $(, $param: FileBackend = BoxedBackend)?> {
+            file_state: FileState<U, $backend>,
+            error_state: Option<PascalIoError>,
+        }
+
+        impl<U $(, $param: FileBackend)?> fmt::Debug for $name<U $(, $param)?> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct(stringify!($name))
+                    .field("error_state", &self.error_state)
+                    .finish_non_exhaustive()
+            }
+        }
+
+        impl<U: TextUnit $(, $param: FileBackend)?> PascalFile for $name<U $(, $param)?> {
+            type Unit = U;
+
+            type Backend = $backend;
+
+            fn is_text_file() -> bool {
+                true
+            }
+
+            fn is_eoln_unit(unit: &U) -> bool {
+                unit.to_char() == '\n'
+            }
+
+            fn eoln_unit() -> U {
+                U::eoln_unit()
+            }
+
+            fn open_text_file_for_read(
+                $read_path: &str,
+            ) -> Result<(<$backend as FileBackend>::LineReader, bool), PascalIoError> {
+                $open_read
+            }
+
+            fn open_binary_file_for_read(
+                _: &str,
+            ) -> Result<<$backend as FileBackend>::Reader, PascalIoError> {
+                Err(unsupported_operation())
+            }
+
+            fn open_file_for_write(
+                $write_path: &str,
+            ) -> Result<<$backend as FileBackend>::Writer, PascalIoError> {
+                $open_write
+            }
+
+            $(
+                fn open_file_for_append(
+                    $append_path: &str,
+                ) -> Result<(<$backend as FileBackend>::Writer, u64), PascalIoError> {
+                    $open_append
+                }
+            )?
+
+            fn convert_line_bytes_crlf_to_lf(input: &mut Vec<u8>) {
+                convert_crlf_to_lf(input)
+            }
+
+            fn convert_line_bytes_to_units(
+                input: &[u8],
+                units: &mut Vec<U>,
+            ) -> Result<(), PascalIoError> {
+                U::convert_bytes_to_units(input, units);
+                Ok(())
+            }
+
+            fn convert_str_to_units(
+                input: &str,
+                units: &mut Vec<U>,
+            ) -> Result<(), PascalIoError> {
+                crate::TextEncoding::Utf8.convert_str_to_units(input, units)
+            }
+
+            fn convert_unit_to_str(data: U, text: &mut String) -> Result<(), PascalIoError> {
+                crate::TextEncoding::Utf8.convert_unit_to_str(data, text)
+            }
+
+            fn convert_blob_to_unit(_: &[u8]) -> U {
+                unreachable!("text files are never read in block mode")
+            }
+
+            fn convert_unit_to_blob(
+                data: U,
+                f: &mut dyn for<'a> FnMut(&'a [u8]),
+            ) -> Result<(), PascalIoError> {
+                data.convert_unit_to_bytes(f);
+                Ok(())
+            }
+
+            fn file_state(&self) -> &FileState<U, $backend> {
+                &self.file_state
+            }
+
+            fn file_state_mut(&mut self) -> &mut FileState<U, $backend> {
+                &mut self.file_state
+            }
+
+            fn error_state(&self) -> Option<&PascalIoError> {
+                self.error_state.as_ref()
+            }
+
+            fn set_error_state(&mut self, error_state: Option<PascalIoError>) {
+                self.error_state = error_state;
+            }
+        }
+    };
+}
+
+pub(crate) use impl_text_file;
+
+impl_text_file!(
+    TextInput,
+    backend(BoxedBackend),
+    open_read(_) {
+        let stdin = io::stdin();
+        let is_terminal = stdin.is_terminal();
+        Ok((Box::new(stdin), is_terminal))
+    },
+    open_write(_) Err(unsupported_operation()),
+    open_append(_) Err(unsupported_operation())
+);
+
+impl_text_file!(
+    TextOutput,
+    backend(BoxedBackend),
+    open_read(_) Err(unsupported_operation()),
+    open_write(_) Ok(Box::new(io::stdout())),
+    open_append(_) Ok((Box::new(io::stdout()), 0))
+);
+
+impl_text_file!(
+    TextError,
+    backend(BoxedBackend),
+    open_read(_) Err(unsupported_operation()),
+    open_write(_) Ok(Box::new(io::stderr())),
+    open_append(_) Ok((Box::new(io::stderr()), 0))
+);
+
+// Like Pascal's `input` and `output`, these come already opened.
+impl<U: TextUnit> TextInput<U> {
+    pub fn new() -> Self {
+        let mut file = TextInput {
+            file_state: FileState::Undefined,
+            error_state: None,
+        };
+        crate::reset(&mut file, "", "");
+        file
+    }
+}
+
+impl<U: TextUnit> TextOutput<U> {
+    pub fn new() -> Self {
+        let mut file = TextOutput {
+            file_state: FileState::Undefined,
+            error_state: None,
+        };
+        // `/W=0` as stdout is already line-buffered, a second buffer would hold
+        // back finished lines and prompts until it fills up.
+        crate::rewrite(&mut file, "", "/W=0");
+        file
+    }
+}
+
+impl<U: TextUnit> TextError<U> {
+    pub fn new() -> Self {
+        let mut file = TextError {
+            file_state: FileState::Undefined,
+            error_state: None,
+        };
+        // stderr has to stay unbuffered, so messages show up right away.
+        crate::rewrite(&mut file, "", "/W=0");
+        file
+    }
+}
+
+impl<U: TextUnit> Default for TextInput<U> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<U: TextUnit> Default for TextOutput<U> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<U: TextUnit> Default for TextError<U> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,765 @@
+use crate::*;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// A scratch file in the temporary directory, removed again on drop.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(contents: &[u8]) -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let name = format!(
+            "pascal_io_test_{}_{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, contents).unwrap();
+        TempFile(path)
+    }
+
+    fn path(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+
+    fn contents(&self) -> Vec<u8> {
+        fs::read(&self.0).unwrap()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+#[test]
+fn get_on_byte_file_tracks_position() {
+    let temp = TempFile::new(&[10, 20, 30]);
+    let mut file = ByteFile::new();
+    reset(&mut file, temp.path(), "");
+    get(&mut file);
+    assert_eq!(position(&mut file), 1);
+    assert_eq!(buffer_variable(&mut file), 20);
+    get(&mut file);
+    get(&mut file);
+    assert!(eof(&mut file));
+}
+
+#[test]
+fn seek_inside_and_outside_block() {
+    let contents: Vec<u8> = (0..100).collect();
+    let temp = TempFile::new(&contents);
+    let mut file = ByteFile::new();
+    reset(&mut file, temp.path(), "/K=8");
+    assert_eq!(buffer_variable(&mut file), 0);
+    seek(&mut file, 5);
+    assert_eq!(position(&mut file), 5);
+    assert_eq!(buffer_variable(&mut file), 5);
+    seek(&mut file, 50);
+    assert_eq!(buffer_variable(&mut file), 50);
+    seek(&mut file, 3);
+    assert_eq!(buffer_variable(&mut file), 3);
+    get(&mut file);
+    assert_eq!(buffer_variable(&mut file), 4);
+    seek(&mut file, 99);
+    assert_eq!(buffer_variable(&mut file), 99);
+    get(&mut file);
+    assert!(eof(&mut file));
+    seek(&mut file, 200);
+    assert!(eof(&mut file));
+    seek(&mut file, 0);
+    assert!(!eof(&mut file));
+    assert_eq!(buffer_variable(&mut file), 0);
+}
+
+#[test]
+fn patch_binary_rejects_offsets_past_written_data() {
+    let temp = TempFile::new(b"");
+    let mut file = ByteFile::new();
+    rewrite(&mut file, temp.path(), "");
+    write_units(&mut file, &[1, 2, 3]);
+    assert!(try_patch_binary(&mut file, 3, 9u8).is_err());
+    assert!(try_patch_binary(&mut file, u64::MAX, 9u8).is_err());
+    patch_binary(&mut file, 1, 9u8);
+    close(&mut file);
+    assert_eq!(temp.contents(), [1, 9, 3]);
+}
+
+#[test]
+fn update_read_modify_write() {
+    let temp = TempFile::new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    let mut file = ByteFile::new();
+    update(&mut file, temp.path(), "/K=4");
+    assert_eq!(buffer_variable(&mut file), 0);
+    get(&mut file);
+    get(&mut file);
+    assert_eq!(buffer_variable(&mut file), 2);
+    buffer_variable_assign(&mut file, 20);
+    put(&mut file);
+    assert_eq!(position(&mut file), 3);
+    assert_eq!(buffer_variable(&mut file), 3);
+    seek(&mut file, 2);
+    assert_eq!(buffer_variable(&mut file), 20);
+    // across the end of the buffered block.
+    seek(&mut file, 3);
+    buffer_variable_assign(&mut file, 30);
+    put(&mut file);
+    assert_eq!(buffer_variable(&mut file), 4);
+    seek(&mut file, 9);
+    buffer_variable_assign(&mut file, 90);
+    put(&mut file);
+    assert!(eof(&mut file));
+    close(&mut file);
+    assert_eq!(temp.contents(), [0, 1, 20, 30, 4, 5, 6, 7, 8, 90]);
+}
+
+#[test]
+fn update_put_before_reading() {
+    let temp = TempFile::new(&[0, 1, 2]);
+    let mut file = ByteFile::new();
+    update(&mut file, temp.path(), "");
+    buffer_variable_assign(&mut file, 7);
+    put(&mut file);
+    assert_eq!(position(&mut file), 1);
+    assert_eq!(buffer_variable(&mut file), 1);
+    close(&mut file);
+    assert_eq!(temp.contents(), [7, 1, 2]);
+}
+
+fn read_lines(path: &str, options: &str) -> Vec<String> {
+    let mut file = TextFile::<char>::new();
+    reset(&mut file, path, options);
+    let mut lines = vec![];
+    while !eof(&mut file) {
+        let mut line = String::new();
+        while !eoln(&mut file) {
+            line.push(buffer_variable(&mut file));
+            get(&mut file);
+        }
+        read_ln(&mut file);
+        lines.push(line);
+    }
+    lines
+}
+
+#[test]
+fn line_endings() {
+    let temp = TempFile::new(b"a\rb\r\nc\nd");
+    assert_eq!(read_lines(temp.path(), ""), ["a", "b", "c", "d"]);
+    assert_eq!(read_lines(temp.path(), "/L=LF"), ["a\rb\r", "c", "d"]);
+    assert_eq!(read_lines(temp.path(), "/L=CRLF"), ["a\rb", "c", "d"]);
+    let temp = TempFile::new(b"a\rb\r\rc");
+    assert_eq!(read_lines(temp.path(), "/L=CR"), ["a", "b", "", "c"]);
+}
+
+#[test]
+fn crlf_split_across_reads() {
+    let mut contents = vec![b'x'; 8191];
+    contents.extend_from_slice(b"\r\ny\r\nz");
+    let temp = TempFile::new(&contents);
+    let lines = read_lines(temp.path(), "");
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0].len(), 8191);
+    assert_eq!(lines[1..], ["y", "z"]);
+}
+
+#[test]
+fn get_advances_past_unloaded_unit() {
+    let temp = TempFile::new(b"ab\ncd\n");
+    let expected = ['a', 'b', '\n', 'c', 'd', '\n'];
+    for (gets, &unit) in expected.iter().enumerate() {
+        let mut file = TextFile::<char>::new();
+        reset(&mut file, temp.path(), "");
+        for _ in 0..gets {
+            get(&mut file);
+        }
+        assert_eq!(buffer_variable(&mut file), unit, "after {} gets", gets);
+    }
+}
+
+#[test]
+fn max_line_length_split() {
+    let temp = TempFile::new(b"abcdefg\nhi");
+    assert_eq!(read_lines(temp.path(), "/M=4,SPLIT"), ["abcd", "efg", "hi"]);
+}
+
+#[test]
+fn max_line_length_truncate() {
+    let temp = TempFile::new(b"abcdefg\nhi");
+    let mut file = TextFile::<char>::new();
+    reset(&mut file, temp.path(), "/M=4,TRUNCATE");
+    assert!(line_truncated(&mut file));
+    read_ln(&mut file);
+    assert_eq!(buffer_variable(&mut file), 'h');
+    assert!(!line_truncated(&mut file));
+    assert_eq!(read_lines(temp.path(), "/M=4,TRUNCATE"), ["abcd", "hi"]);
+}
+
+#[test]
+fn max_line_length_split_at_end_of_file() {
+    let temp = TempFile::new(b"abcd");
+    assert_eq!(read_lines(temp.path(), "/M=4,SPLIT"), ["abcd"]);
+    let temp = TempFile::new(b"abcdefgh");
+    assert_eq!(read_lines(temp.path(), "/M=4,SPLIT"), ["abcd", "efgh"]);
+}
+
+#[test]
+fn max_line_length_error_skips_line() {
+    let temp = TempFile::new(b"abcdefg\nhi\r\nlongest");
+    let mut file = TextFile::<char>::new();
+    reset(&mut file, temp.path(), "/M=4,ERROR");
+    assert!(matches!(
+        try_buffer_variable(&mut file),
+        Err(PascalIoError::LineLimitExceeded)
+    ));
+    assert_eq!(buffer_variable(&mut file), 'h');
+    read_ln(&mut file);
+    assert!(matches!(
+        try_eoln(&mut file),
+        Err(PascalIoError::LineLimitExceeded)
+    ));
+    assert!(eof(&mut file));
+
+    let temp = TempFile::new(b"abcdefg\nhi\nlongest\nok");
+    reset(&mut file, temp.path(), "/M=4,ERROR/O");
+    get(&mut file);
+    assert_eq!(erstat(&mut file), 16);
+    assert_eq!(buffer_variable(&mut file), 'h');
+    read_ln(&mut file);
+    get(&mut file);
+    assert_eq!(buffer_variable(&mut file), 'o');
+}
+
+#[test]
+fn read_units_at_end_of_file() {
+    let temp = TempFile::new(&[1, 2, 3, 4, 5]);
+    let mut file = ByteFile::new();
+    reset(&mut file, temp.path(), "/K=2");
+    let mut units = [0; 3];
+    assert_eq!(read_units(&mut file, &mut units), 3);
+    assert_eq!(units, [1, 2, 3]);
+    assert_eq!(read_units(&mut file, &mut units), 2);
+    assert_eq!(units[..2], [4, 5]);
+    assert!(eof(&mut file));
+    assert_eq!(read_units(&mut file, &mut units), 0);
+}
+
+#[test]
+fn byte_text_file_transcodes_to_character_codes() {
+    let temp = TempFile::new(&[0x61, 0x80, 0xE9]);
+    let mut file = TextFile::<u8>::new();
+    reset(&mut file, temp.path(), "/E=LATIN1");
+    let mut units = vec![];
+    while !eoln(&mut file) {
+        units.push(buffer_variable(&mut file));
+        get(&mut file);
+    }
+    assert_eq!(units, [0x61, 0x80, 0xE9]);
+
+    rewrite(&mut file, temp.path(), "/E=LATIN1");
+    buffer_variable_assign(&mut file, 0xE9);
+    put(&mut file);
+    close(&mut file);
+    assert_eq!(temp.contents(), [0xE9]);
+}
+
+#[test]
+fn char_text_file_transcodes_single_byte_encodings() {
+    let temp = TempFile::new(&[0x80, 0x9B]);
+    assert_eq!(read_lines(temp.path(), "/E=CP437"), ["\u{C7}\u{A2}"]);
+    assert_eq!(read_lines(temp.path(), "/E=CP1252"), ["\u{20AC}\u{203A}"]);
+}
+
+#[test]
+fn text_file_with_fs_backend() {
+    let temp = TempFile::new(b"");
+    let mut file = TextFile::<char, FsBackend>::default();
+    rewrite(&mut file, temp.path(), "");
+    pascal_writeln!(&mut file, "first");
+    pascal_write!(&mut file, "second");
+    close(&mut file);
+    assert_eq!(temp.contents(), b"first\nsecond");
+    assert_eq!(read_lines(temp.path(), ""), ["first", "second"]);
+    let mut file = TextFile::<u8, FsBackend>::default();
+    reset(&mut file, temp.path(), "");
+    assert_eq!(buffer_variable(&mut file), b'f');
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn error_state_mode_records_failed_buffered_writes() {
+    let mut file = ByteFile::new();
+    rewrite(&mut file, "/dev/full", "/O");
+    write_units(&mut file, &[7; 20000]);
+    assert_ne!(erstat(&mut file), 0);
+    drop(file);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn error_state_mode_records_failed_output_on_reopen() {
+    let temp = TempFile::new(b"");
+    let mut file = ByteFile::new();
+    rewrite(&mut file, "/dev/full", "/O");
+    write_units(&mut file, &[1, 2, 3]);
+    assert_eq!(erstat(&mut file), 0);
+    rewrite(&mut file, temp.path(), "/O");
+    assert_ne!(erstat(&mut file), 0);
+    write_units(&mut file, &[4, 5]);
+    close(&mut file);
+    assert_eq!(temp.contents(), [4, 5]);
+
+    rewrite(&mut file, "/dev/full", "/O");
+    write_units(&mut file, &[1, 2, 3]);
+    drop(file);
+}
+
+#[test]
+fn error_state_mode_keeps_output_before_failure() {
+    let temp = TempFile::new(b"");
+    let mut file = ByteFile::new();
+    rewrite(&mut file, temp.path(), "/O");
+    write_units(&mut file, &[1, 2]);
+    put(&mut file);
+    assert_eq!(
+        erstat(&mut file),
+        PascalIoError::UndefinedBufferVariable.code()
+    );
+    assert_eq!(temp.contents(), [1, 2]);
+}
+
+// A binary file of two-byte units, to read files that end in half a unit.
+#[derive(Debug, Default)]
+struct PairFile {
+    file_state: FileState<[u8; 2]>,
+    error_state: Option<PascalIoError>,
+}
+
+impl PascalFile for PairFile {
+    type Unit = [u8; 2];
+
+    type Backend = BoxedBackend;
+
+    fn is_text_file() -> bool {
+        false
+    }
+
+    fn is_eoln_unit(_: &[u8; 2]) -> bool {
+        false
+    }
+
+    fn eoln_unit() -> [u8; 2] {
+        [0; 2]
+    }
+
+    fn open_text_file_for_read(
+        _: &str,
+    ) -> Result<(<BoxedBackend as FileBackend>::LineReader, bool), PascalIoError> {
+        unreachable!()
+    }
+
+    fn open_binary_file_for_read(
+        path: &str,
+    ) -> Result<<BoxedBackend as FileBackend>::Reader, PascalIoError> {
+        BoxedBackend::open_reader(path)
+    }
+
+    fn open_file_for_write(
+        path: &str,
+    ) -> Result<<BoxedBackend as FileBackend>::Writer, PascalIoError> {
+        BoxedBackend::open_writer(path)
+    }
+
+    fn convert_line_bytes_crlf_to_lf(_: &mut Vec<u8>) {
+        unreachable!()
+    }
+
+    fn convert_line_bytes_to_units(_: &[u8], _: &mut Vec<[u8; 2]>) -> Result<(), PascalIoError> {
+        unreachable!()
+    }
+
+    fn convert_blob_to_unit(input: &[u8]) -> [u8; 2] {
+        [input[0], input[1]]
+    }
+
+    fn convert_unit_to_blob(
+        data: [u8; 2],
+        f: &mut dyn for<'a> FnMut(&'a [u8]),
+    ) -> Result<(), PascalIoError> {
+        f(&data);
+        Ok(())
+    }
+
+    fn file_state(&self) -> &FileState<[u8; 2]> {
+        &self.file_state
+    }
+
+    fn file_state_mut(&mut self) -> &mut FileState<[u8; 2]> {
+        &mut self.file_state
+    }
+
+    fn error_state(&self) -> Option<&PascalIoError> {
+        self.error_state.as_ref()
+    }
+
+    fn set_error_state(&mut self, error_state: Option<PascalIoError>) {
+        self.error_state = error_state;
+    }
+}
+
+#[test]
+fn read_units_keeps_count_before_truncated_unit() {
+    let contents: Vec<u8> = (0..21).collect();
+    let temp = TempFile::new(&contents);
+    for options in ["", "/K=4"] {
+        let mut file = PairFile::default();
+        reset(&mut file, temp.path(), options);
+        let mut units = [[0; 2]; 16];
+        assert_eq!(try_read_units(&mut file, &mut units).unwrap(), 10);
+        assert_eq!(units[9], [18, 19]);
+        assert!(matches!(
+            try_read_units(&mut file, &mut units),
+            Err(PascalIoError::TruncatedUnit)
+        ));
+        assert_eq!(try_read_units(&mut file, &mut units).unwrap(), 0);
+        assert!(eof(&mut file));
+    }
+}
+
+#[test]
+fn binary_integers_are_big_endian() {
+    let temp = TempFile::new(b"");
+    let mut file = ByteFile::new();
+    rewrite(&mut file, temp.path(), "");
+    write_binary(&mut file, 0x0102u16);
+    write_binary(&mut file, -2i32);
+    write_binary(&mut file, 0x7Fi8);
+    close(&mut file);
+    assert_eq!(temp.contents(), [1, 2, 0xFF, 0xFF, 0xFF, 0xFE, 0x7F]);
+    reset(&mut file, temp.path(), "");
+    assert_eq!(read_binary::<_, u16>(&mut file), 0x0102);
+    assert_eq!(read_binary::<_, i32>(&mut file), -2);
+    assert_eq!(read_binary::<_, i8>(&mut file), 0x7F);
+    assert!(eof(&mut file));
+}
+
+fn pascal<T: PascalWrite + ?Sized>(
+    value: &T,
+    width: Option<usize>,
+    precision: Option<usize>,
+) -> String {
+    PascalFormatted::new(value, width, precision).to_string()
+}
+
+#[test]
+fn integers_are_right_justified() {
+    assert_eq!(pascal(&42, None, None), "        42");
+    assert_eq!(pascal(&-7i64, Some(3), None), " -7");
+    assert_eq!(pascal(&12345u16, Some(2), None), "12345");
+}
+
+#[test]
+fn reals_default_to_exponent_form() {
+    assert_eq!(pascal(&3.5, None, None), " 3.500000000000000E+00");
+    assert_eq!(pascal(&-3.5, None, None), "-3.500000000000000E+00");
+    assert_eq!(pascal(&0.0, None, None), " 0.000000000000000E+00");
+    assert_eq!(pascal(&1.5e-3, Some(10), None), " 1.500E-03");
+    assert_eq!(pascal(&2.0f32, Some(1), None), " 2.0E+00");
+    assert_eq!(pascal(&f64::INFINITY, Some(5), None), "  Inf");
+}
+
+#[test]
+fn reals_in_fixed_point_form() {
+    assert_eq!(pascal(&1.23456, Some(8), Some(3)), "   1.235");
+    assert_eq!(pascal(&-2.5, Some(1), Some(1)), "-2.5");
+    assert_eq!(pascal(&-0.0001, Some(6), Some(2)), "  0.00");
+    assert_eq!(pascal(&-0.0, None, Some(1)), "0.0");
+}
+
+#[test]
+fn booleans_chars_and_strings() {
+    assert_eq!(pascal(&true, None, None), " TRUE");
+    assert_eq!(pascal(&false, None, None), "FALSE");
+    assert_eq!(pascal(&false, Some(7), None), "  FALSE");
+    assert_eq!(pascal(&true, Some(2), None), "TR");
+    assert_eq!(pascal(&'x', None, None), "x");
+    assert_eq!(pascal(&'x', Some(3), None), "  x");
+    assert_eq!(pascal("abc", None, None), "abc");
+    assert_eq!(pascal("abc", Some(5), None), "  abc");
+    assert_eq!(pascal("abc", Some(2), None), "ab");
+    assert_eq!(
+        pascal(&String::from("\u{e9}t\u{e9}"), Some(2), None),
+        "\u{e9}t"
+    );
+}
+
+#[test]
+fn read_integer_skips_blanks_and_line_ends() {
+    let temp = TempFile::new(b"  12\n\n\t-34 +5\n99999999999");
+    let mut file = TextFile::<char>::new();
+    reset(&mut file, temp.path(), "");
+    assert_eq!(try_read_integer::<_, i32>(&mut file).unwrap(), 12);
+    assert_eq!(try_read_integer::<_, i32>(&mut file).unwrap(), -34);
+    assert_eq!(try_read_integer::<_, i32>(&mut file).unwrap(), 5);
+    assert!(matches!(
+        try_read_integer::<_, i32>(&mut file),
+        Err(PascalIoError::NumberOverflow)
+    ));
+    assert!(matches!(
+        try_read_integer::<_, i32>(&mut file),
+        Err(PascalIoError::Eof)
+    ));
+
+    reset(&mut file, temp.path(), "");
+    for _ in 0..3 {
+        read_integer::<_, i32>(&mut file);
+    }
+    assert_eq!(read_integer::<_, i64>(&mut file), 99999999999);
+}
+
+#[test]
+fn read_real_forms() {
+    let temp = TempFile::new(b"1.5 -2e3\n7 +0.25E-1 1e400");
+    let mut file = TextFile::<char>::new();
+    reset(&mut file, temp.path(), "");
+    assert_eq!(try_read_real(&mut file).unwrap(), 1.5);
+    assert_eq!(try_read_real(&mut file).unwrap(), -2000.0);
+    assert_eq!(try_read_real(&mut file).unwrap(), 7.0);
+    assert_eq!(try_read_real(&mut file).unwrap(), 0.025);
+    assert!(matches!(
+        try_read_real(&mut file),
+        Err(PascalIoError::NumberOverflow)
+    ));
+    assert!(matches!(try_read_real(&mut file), Err(PascalIoError::Eof)));
+}
+
+#[test]
+fn read_number_rejects_malformed_input() {
+    let temp = TempFile::new(b"+ 5 .5");
+    let mut file = TextFile::<char>::new();
+    reset(&mut file, temp.path(), "");
+    assert!(matches!(
+        try_read_integer::<_, i32>(&mut file),
+        Err(PascalIoError::InvalidNumber)
+    ));
+    assert_eq!(try_read_integer::<_, i32>(&mut file).unwrap(), 5);
+    assert!(matches!(
+        try_read_real(&mut file),
+        Err(PascalIoError::InvalidNumber)
+    ));
+
+    reset(&mut file, temp.path(), "/O");
+    assert_eq!(read_integer::<_, i32>(&mut file), 0);
+    assert_eq!(erstat(&mut file), PascalIoError::InvalidNumber.code());
+    assert_eq!(read_integer::<_, i32>(&mut file), 5);
+}
+
+#[test]
+fn options_parse() {
+    let default = FileOptions::default();
+    let cases = [
+        ("", default),
+        ("  ", default),
+        (
+            "/O",
+            FileOptions {
+                error_state_mode: true,
+                ..default
+            },
+        ),
+        (
+            " /i / a /s ",
+            FileOptions {
+                interactive: Some(true),
+                append: true,
+                sync_on_close: true,
+                ..default
+            },
+        ),
+        (
+            "/B/L=crlf",
+            FileOptions {
+                interactive: Some(false),
+                line_ending: LineEnding::CrLf,
+                ..default
+            },
+        ),
+        (
+            "/e = Latin-1",
+            FileOptions {
+                encoding: Some(TextEncoding::Latin1),
+                ..default
+            },
+        ),
+        (
+            "/M=80",
+            FileOptions {
+                max_line_length: Some(80),
+                ..default
+            },
+        ),
+        (
+            "/m=80, split",
+            FileOptions {
+                max_line_length: Some(80),
+                line_overflow: LineOverflow::Split,
+                ..default
+            },
+        ),
+        (
+            "/K=4/W=0",
+            FileOptions {
+                block_size: Some(4),
+                write_buffer_capacity: Some(0),
+                ..default
+            },
+        ),
+    ];
+    for (options, expected) in cases {
+        assert_eq!(
+            options.parse::<FileOptions>().unwrap(),
+            expected,
+            "{:?}",
+            options
+        );
+    }
+}
+
+#[test]
+fn options_reject_invalid_switches() {
+    let cases = [
+        ("O", "O"),
+        ("x/O", "x/O"),
+        ("/X", "/X"),
+        ("/O//I", "/"),
+        ("/O=1", "/O=1"),
+        ("/L", "/L"),
+        ("/L=CRCR", "/L=CRCR"),
+        ("/E=EBCDIC", "/E=EBCDIC"),
+        ("/M=0", "/M=0"),
+        ("/M=80,WRAP", "/M=80,WRAP"),
+        ("/K=0", "/K=0"),
+        ("/K=-1", "/K=-1"),
+        ("/W=lots", "/W=lots"),
+    ];
+    for (options, invalid) in cases {
+        match options.parse::<FileOptions>() {
+            Err(PascalIoError::InvalidOption(option)) => assert_eq!(option, invalid),
+            result => panic!("{:?} parsed as {:?}", options, result),
+        }
+    }
+}
+
+#[test]
+fn input_ln_reads_whole_lines() {
+    let temp = TempFile::new(b"ab  \t\nlonger line\n\nlast ");
+    let mut file = TextFile::<char>::new();
+    reset(&mut file, temp.path(), "");
+    let mut buffer = ['\0'; 16];
+    let strip = TrailingBlanks::StripSpaces;
+    assert_eq!(input_ln(&mut file, false, &mut buffer, strip), Some(5));
+    assert_eq!(buffer[..5], ['a', 'b', ' ', ' ', '\t']);
+    // without `bypass_eoln`, the file stays at the end of the same line.
+    assert_eq!(input_ln(&mut file, false, &mut buffer, strip), Some(0));
+    assert_eq!(input_ln(&mut file, true, &mut buffer, strip), Some(11));
+    assert_eq!(input_ln(&mut file, true, &mut buffer, strip), Some(0));
+    assert_eq!(input_ln(&mut file, true, &mut buffer, strip), Some(4));
+    assert_eq!(input_ln(&mut file, true, &mut buffer, strip), None);
+
+    reset(&mut file, temp.path(), "");
+    let strip = TrailingBlanks::StripSpacesAndTabs;
+    assert_eq!(input_ln(&mut file, false, &mut buffer, strip), Some(2));
+    for _ in 0..2 {
+        input_ln(&mut file, true, &mut buffer, strip);
+    }
+    let keep = TrailingBlanks::Keep;
+    assert_eq!(input_ln(&mut file, true, &mut buffer, keep), Some(5));
+    assert_eq!(buffer[..5], ['l', 'a', 's', 't', ' ']);
+}
+
+#[test]
+fn input_ln_stops_at_first_unit_that_does_not_fit() {
+    let temp = TempFile::new(b"abcdef\nxy");
+    let mut file = TextFile::<char>::new();
+    reset(&mut file, temp.path(), "");
+    let mut buffer = ['\0'; 4];
+    let strip = TrailingBlanks::StripSpaces;
+    assert!(matches!(
+        try_input_ln(&mut file, false, &mut buffer, strip),
+        Err(PascalIoError::LineTooLong)
+    ));
+    assert_eq!(buffer, ['a', 'b', 'c', 'd']);
+    assert_eq!(buffer_variable(&mut file), 'e');
+    assert_eq!(input_ln(&mut file, false, &mut buffer, strip), Some(2));
+    assert_eq!(buffer[..2], ['e', 'f']);
+
+    reset(&mut file, temp.path(), "/O");
+    assert_eq!(input_ln(&mut file, false, &mut buffer, strip), None);
+    assert_eq!(erstat(&mut file), PascalIoError::LineTooLong.code());
+    assert_eq!(input_ln(&mut file, false, &mut buffer, strip), Some(2));
+    assert_eq!(input_ln(&mut file, true, &mut buffer, strip), Some(2));
+    assert_eq!(buffer[..2], ['x', 'y']);
+}
+
+#[test]
+fn extend_keeps_existing_contents() {
+    let temp = TempFile::new(&[1, 2, 3]);
+    let mut file = ByteFile::new();
+    extend(&mut file, temp.path(), "");
+    assert_eq!(write_position(&mut file), 3);
+    write_units(&mut file, &[4, 5]);
+    assert_eq!(write_position(&mut file), 5);
+    assert!(matches!(
+        try_patch_binary(&mut file, 0, 9u8),
+        Err(PascalIoError::NotSeekable)
+    ));
+    close(&mut file);
+    assert_eq!(temp.contents(), [1, 2, 3, 4, 5]);
+
+    rewrite(&mut file, temp.path(), "/A");
+    assert_eq!(write_position(&mut file), 5);
+    write_units(&mut file, &[6]);
+    close(&mut file);
+    assert_eq!(temp.contents(), [1, 2, 3, 4, 5, 6]);
+
+    let temp = TempFile::new(b"first\n");
+    let mut file = TextFile::<char>::new();
+    extend(&mut file, temp.path(), "");
+    pascal_writeln!(&mut file, "second");
+    close(&mut file);
+    assert_eq!(temp.contents(), b"first\nsecond\n");
+}
+
+#[test]
+fn write_units_in_update_mode() {
+    let contents: Vec<u8> = (0..10).collect();
+    let temp = TempFile::new(&contents);
+    let mut file = ByteFile::new();
+    update(&mut file, temp.path(), "/K=4");
+    write_units(&mut file, &[100]);
+    assert_eq!(position(&mut file), 1);
+    assert_eq!(buffer_variable(&mut file), 1);
+    write_units(&mut file, &[10, 11]);
+    assert_eq!(position(&mut file), 3);
+    assert_eq!(buffer_variable(&mut file), 3);
+    seek(&mut file, 1);
+    assert_eq!(buffer_variable(&mut file), 10);
+    // across the end of the buffered block.
+    seek(&mut file, 3);
+    write_units(&mut file, &[30, 31, 32, 33]);
+    assert_eq!(position(&mut file), 7);
+    assert_eq!(buffer_variable(&mut file), 7);
+    seek(&mut file, 4);
+    assert_eq!(buffer_variable(&mut file), 31);
+    seek(&mut file, 9);
+    write_units(&mut file, &[90, 91, 92]);
+    assert_eq!(position(&mut file), 12);
+    assert!(eof(&mut file));
+    close(&mut file);
+    assert_eq!(
+        temp.contents(),
+        [100, 10, 11, 30, 31, 32, 33, 7, 8, 90, 91, 92]
+    );
+}
@@ -0,0 +1,104 @@
+use pascal_io::*;
+use pascal_io_derive::PascalFile;
+use std::fs;
+
+#[derive(Debug, Default, PascalFile)]
+#[pascal_file(text, encoding = "latin1")]
+struct Latin1File {
+    state: FileState<u8>,
+    error: Option<PascalIoError>,
+}
+
+#[derive(Debug, Default, PascalFile)]
+#[pascal_file(text, unit = "u8", encoding = "cp1252")]
+struct Cp1252File {
+    state: FileState<u8>,
+    error: Option<PascalIoError>,
+}
+
+#[derive(Debug, Default, PascalFile)]
+#[pascal_file(unit = "u8")]
+struct TaggedByteFile(
+    #[pascal_file(state)] FileState<u8>,
+    #[pascal_file(error)] Option<PascalIoError>,
+);
+
+#[derive(Debug, PascalFile)]
+#[pascal_file(text, error_state_mode)]
+struct GenericTextFile<X> {
+    state: FileState<char>,
+    error: Option<PascalIoError>,
+    _extra: X,
+}
+
+#[derive(Debug, Default, PascalFile)]
+#[pascal_file(unit = "u8", backend = "FsBackend")]
+struct FsByteFile {
+    state: FileState<u8, FsBackend>,
+    error: Option<PascalIoError>,
+}
+
+fn temp_path(name: &str) -> String {
+    let path =
+        std::env::temp_dir().join(format!("pascal_io_derive_{}_{}", std::process::id(), name));
+    path.to_str().unwrap().to_owned()
+}
+
+#[test]
+fn derived_text_file_round_trip() {
+    let path = temp_path("text");
+    let mut file = Latin1File::default();
+    rewrite(&mut file, &*path, "");
+    pascal_writeln!(&mut file, "caf\u{e9}");
+    close(&mut file);
+    assert_eq!(fs::read(&path).unwrap(), b"caf\xe9\n");
+    reset(&mut file, &*path, "");
+    assert_eq!(buffer_variable(&mut file), b'c');
+    close(&mut file);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn derived_byte_text_file_keeps_high_bytes() {
+    let path = temp_path("cp1252");
+    fs::write(&path, [0x80, 0xE9]).unwrap();
+    let mut file = Cp1252File::default();
+    reset(&mut file, &*path, "");
+    assert_eq!(buffer_variable(&mut file), 0x80);
+    get(&mut file);
+    assert_eq!(buffer_variable(&mut file), 0xE9);
+    rewrite(&mut file, &*path, "");
+    buffer_variable_assign(&mut file, 0x80);
+    put(&mut file);
+    pascal_write!(&mut file, "\u{20AC}");
+    close(&mut file);
+    assert_eq!(fs::read(&path).unwrap(), [0x80, 0x80]);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn derived_binary_files_round_trip() {
+    let path = temp_path("binary");
+    let mut file = FsByteFile::default();
+    rewrite(&mut file, &*path, "");
+    write_units(&mut file, &[1, 2, 3]);
+    close(&mut file);
+    let mut file = TaggedByteFile::default();
+    reset(&mut file, &*path, "");
+    let mut units = [0; 4];
+    assert_eq!(read_units(&mut file, &mut units), 3);
+    assert_eq!(units[..3], [1, 2, 3]);
+    close(&mut file);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn derived_error_state_mode() {
+    let mut file = GenericTextFile {
+        state: FileState::default(),
+        error: None,
+        _extra: 0u8,
+    };
+    reset(&mut file, temp_path("missing"), "");
+    assert_ne!(erstat(&mut file), 0);
+}